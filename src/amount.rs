@@ -0,0 +1,277 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Program;
+use anchor_spl::token::Mint;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Token amount argument provided by a user for a command that can
+/// either be a decimal value denominated in whole tokens or the
+/// keyword `max` to use the maximum amount available.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenAmount {
+    /// A decimal value with an optional trailing token symbol (e.g. `1.5 USDC`).
+    Value {
+        value: String,
+        symbol: Option<String>,
+    },
+    /// The maximum amount available for the operation.
+    Max,
+}
+
+impl TokenAmount {
+    /// Returns whether the amount is the `max` keyword.
+    pub fn is_max(&self) -> bool {
+        matches!(self, Self::Max)
+    }
+
+    /// Convert the amount into the base units of a mint with the argued decimals,
+    /// or return `None` if the `max` keyword was provided. If `base_units` is set
+    /// then the value is taken as already being denominated in base units.
+    pub fn to_base_units(&self, decimals: u8, base_units: bool) -> Result<Option<u64>> {
+        match self {
+            Self::Max => Ok(None),
+            Self::Value { value, .. } if base_units => value
+                .parse::<u64>()
+                .map(Some)
+                .map_err(|_| anyhow!("base unit amounts must be whole numbers: {}", value)),
+            Self::Value { value, .. } => parse_units(value, decimals).map(Some),
+        }
+    }
+}
+
+impl FromStr for TokenAmount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let trimmed = s.trim();
+
+        if trimmed.eq_ignore_ascii_case("max") {
+            return Ok(Self::Max);
+        }
+
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(trimmed.len());
+        let (value, symbol) = trimmed.split_at(split);
+
+        if value.is_empty() || value.matches('.').count() > 1 || value.ends_with('.') {
+            return Err(anyhow!("invalid token amount: {}", s));
+        }
+
+        let symbol = symbol.trim();
+        if !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(anyhow!("invalid token symbol in amount: {}", s));
+        }
+
+        Ok(Self::Value {
+            value: value.to_owned(),
+            symbol: (!symbol.is_empty()).then(|| symbol.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Max => write!(f, "max"),
+            Self::Value {
+                value,
+                symbol: Some(sym),
+            } => write!(f, "{} {}", value, sym),
+            Self::Value { value, .. } => write!(f, "{}", value),
+        }
+    }
+}
+
+/// An amount of tokens in base units with the decimals and optional symbol
+/// of its mint for displaying in both human and base denominations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiAmount {
+    pub base_units: u64,
+    pub decimals: u8,
+    pub symbol: Option<String>,
+}
+
+impl UiAmount {
+    /// Instantiate a new amount from the base unit value and mint decimals.
+    pub fn new(base_units: u64, decimals: u8) -> Self {
+        Self {
+            base_units,
+            decimals,
+            symbol: None,
+        }
+    }
+
+    /// Set the token symbol displayed after the decimal value.
+    pub fn with_symbol(mut self, symbol: Option<&str>) -> Self {
+        self.symbol = symbol.map(ToOwned::to_owned);
+        self
+    }
+}
+
+impl fmt::Display for UiAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Amounts of mints with more decimals than are supported are only displayed in base units
+        let value = match format_units(self.base_units, self.decimals) {
+            Ok(v) => v,
+            Err(_) => return write!(f, "{} base units", self.base_units),
+        };
+
+        write!(f, "{}", value)?;
+        if let Some(sym) = &self.symbol {
+            write!(f, " {}", sym)?;
+        }
+        write!(f, " ({} base units)", self.base_units)
+    }
+}
+
+/// Format an amount of base units as a decimal value for a mint with the argued decimals.
+pub fn format_units(amount: u64, decimals: u8) -> Result<String> {
+    if decimals == 0 {
+        return Ok(amount.to_string());
+    }
+
+    let divisor = 10u128
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| unsupported_decimals(decimals))?;
+    let whole = amount as u128 / divisor;
+    let frac = format!(
+        "{:0width$}",
+        amount as u128 % divisor,
+        width = decimals as usize
+    );
+    let frac = frac.trim_end_matches('0');
+
+    Ok(if frac.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, frac)
+    })
+}
+
+/// Fetch the number of decimals for the argued token mint account.
+pub fn mint_decimals(program: &Program, mint: &Pubkey) -> Result<u8> {
+    Ok(program.account::<Mint>(*mint)?.decimals)
+}
+
+/// Parse a decimal string value into the base units of a mint with the argued decimals.
+fn parse_units(value: &str, decimals: u8) -> Result<u64> {
+    let (whole, frac) = value.split_once('.').unwrap_or((value, ""));
+
+    let multiplier = 10u64
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| unsupported_decimals(decimals))?;
+
+    if frac.len() > decimals as usize {
+        return Err(anyhow!(
+            "amount {} has more than the {} decimal places supported by the mint",
+            value,
+            decimals
+        ));
+    }
+
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u64>()?
+    };
+    let frac = format!("{:0<width$}", frac, width = decimals as usize);
+    let frac = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<u64>()?
+    };
+
+    whole
+        .checked_mul(multiplier)
+        .and_then(|w| w.checked_add(frac))
+        .ok_or_else(|| anyhow!("amount {} is too large", value))
+}
+
+/// Create the error for a mint with more decimals than its amounts can be converted with.
+fn unsupported_decimals(decimals: u8) -> anyhow::Error {
+    anyhow!("mints with {} decimals are not supported", decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_token_amount_variants() {
+        assert_eq!(TokenAmount::from_str("max").unwrap(), TokenAmount::Max);
+        assert_eq!(
+            TokenAmount::from_str("1.5 USDC").unwrap(),
+            TokenAmount::Value {
+                value: "1.5".into(),
+                symbol: Some("USDC".into()),
+            }
+        );
+        assert_eq!(
+            TokenAmount::from_str("20").unwrap(),
+            TokenAmount::Value {
+                value: "20".into(),
+                symbol: None,
+            }
+        );
+        assert!(TokenAmount::from_str("1.2.3").is_err());
+        assert!(TokenAmount::from_str("USDC").is_err());
+        assert!(TokenAmount::from_str("-1").is_err());
+    }
+
+    #[test]
+    fn token_amount_to_base_units() {
+        let amt = TokenAmount::from_str("1.5 USDC").unwrap();
+        assert_eq!(amt.to_base_units(6, false).unwrap(), Some(1_500_000));
+        assert!(amt.to_base_units(0, false).is_err());
+        assert!(amt.to_base_units(6, true).is_err());
+
+        let raw = TokenAmount::from_str("1500000").unwrap();
+        assert_eq!(raw.to_base_units(6, true).unwrap(), Some(1_500_000));
+        assert_eq!(TokenAmount::Max.to_base_units(6, false).unwrap(), None);
+
+        let too_large = TokenAmount::from_str("18446744073709551616").unwrap();
+        assert!(too_large.to_base_units(0, false).is_err());
+        assert!(amt.to_base_units(20, false).is_err());
+    }
+
+    #[test]
+    fn format_base_units() {
+        assert_eq!(format_units(1_500_000, 6).unwrap(), "1.5");
+        assert_eq!(format_units(1_000_000, 6).unwrap(), "1");
+        assert_eq!(format_units(1, 9).unwrap(), "0.000000001");
+        assert_eq!(format_units(42, 0).unwrap(), "42");
+        assert_eq!(
+            format_units(1, 38).unwrap(),
+            format!("0.{}1", "0".repeat(37))
+        );
+        assert!(format_units(1, 39).is_err());
+        assert_eq!(UiAmount::new(1, 39).to_string(), "1 base units");
+        assert_eq!(
+            UiAmount::new(1_500_000, 6).to_string(),
+            "1.5 (1500000 base units)"
+        );
+        assert_eq!(
+            UiAmount::new(1_500_000, 6)
+                .with_symbol(Some("USDC"))
+                .to_string(),
+            "1.5 USDC (1500000 base units)"
+        );
+    }
+}
@@ -23,10 +23,10 @@ use jet_rewards::state::Airdrop;
 use jet_rewards::{accounts, instruction};
 use jet_staking::state::StakePool;
 
-use super::staking::DEFAULT_STAKE_POOL;
+use super::staking::resolve_stake_pool;
 
-use crate::config::{Config, Overrides};
-use crate::program::{create_program_client, send_with_approval};
+use crate::config::{parse_address, Config, JetProgram, Overrides};
+use crate::program::{create_program_client, create_read_only_client, send_with_approval};
 use crate::pubkey::{derive_stake_account, derive_voter_weight_record};
 use crate::terminal::{print_serialized, DisplayOptions};
 
@@ -36,7 +36,7 @@ pub enum AirdropCommand {
     /// Get account data for airdrop account.
     Account {
         /// Base-58 public key of the account.
-        #[clap(value_parser)]
+        #[clap(value_parser = parse_address)]
         address: Pubkey,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
@@ -48,7 +48,7 @@ pub enum AirdropCommand {
     /// Claim rewards airdrop.
     Claim {
         /// The public key of the target airdrop.
-        #[clap(value_parser)]
+        #[clap(value_parser = parse_address)]
         airdrop: Pubkey,
     },
     /// List all airdrops for a stake pool.
@@ -60,34 +60,46 @@ pub enum AirdropCommand {
         #[clap(long, value_parser)]
         pretty: bool,
         /// The stake pool associated with the airdrop(s).
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        stake_pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        stake_pool: Option<Pubkey>,
     },
 }
 
 /// The main entry point and handler for all rewards
 /// program interaction commands.
-pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &AirdropCommand) -> Result<()> {
-    let cfg = Config::new(overrides, *program_id)?;
+pub fn entry(
+    overrides: &Overrides,
+    program_id: &Option<Pubkey>,
+    subcmd: &AirdropCommand,
+) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::Rewards, *program_id)?;
     match subcmd {
         AirdropCommand::Account {
             address,
             json,
             pretty,
-        } => process_get_account(&cfg, address, DisplayOptions::from_args(*json, *pretty)),
+        } => process_get_account(
+            &cfg,
+            address,
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
         AirdropCommand::Claim { airdrop } => process_claim(&cfg, airdrop),
         AirdropCommand::List {
             json,
             pretty,
             stake_pool,
-        } => process_list(&cfg, stake_pool, DisplayOptions::from_args(*json, *pretty)),
+        } => process_list(
+            &cfg,
+            &resolve_stake_pool(&cfg, stake_pool),
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
     }
 }
 
 /// The function handler to get the airdrop program account of the argued public key
 /// and display the content in the terminal for observation.
 fn process_get_account(cfg: &Config, address: &Pubkey, display: DisplayOptions) -> Result<()> {
-    let (program, _) = create_program_client(cfg);
+    let program = create_read_only_client(cfg);
     print_serialized(program.account::<Airdrop>(*address)?, &display)
 }
 
@@ -95,8 +107,9 @@ fn process_get_account(cfg: &Config, address: &Pubkey, display: DisplayOptions)
 /// that they provide the public key for.
 fn process_claim(cfg: &Config, airdrop: &Pubkey) -> Result<()> {
     // Instantiate program clients for both jet_rewards and jet_staking programs
-    let (rewards_program, signer) = create_program_client(cfg);
-    let (staking_program, _) = create_program_client(&cfg.clone_with_program(jet_staking::ID)); // TODO: make configurable override (?)
+    let (rewards_program, signer) = create_program_client(cfg)?;
+    let staking_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::Staking)));
 
     // Fetch the required program account data to retrieve PDAs required for instructions
     let Airdrop {
@@ -142,7 +155,7 @@ fn process_claim(cfg: &Config, airdrop: &Pubkey) -> Result<()> {
 /// The function handler for retrieving and displaying the list of airdrop accounts
 /// discovered via their stake pool association with the provided public key.
 fn process_list(cfg: &Config, pool: &Pubkey, display: DisplayOptions) -> Result<()> {
-    let (program, _) = create_program_client(cfg);
+    let program = create_read_only_client(cfg);
 
     let filters = vec![
         RpcFilterType::DataSize(8 + std::mem::size_of::<Airdrop>() as u64),
@@ -20,9 +20,9 @@ use anyhow::Result;
 use clap::Subcommand;
 use jet_auth::{accounts, instruction, UserAuthentication};
 
-use crate::config::{Config, Overrides};
+use crate::config::{parse_address, Config, JetProgram, Overrides};
 use crate::macros::*;
-use crate::program::{create_program_client, send_with_approval};
+use crate::program::{create_program_client, create_read_only_client, send_with_approval};
 use crate::pubkey::derive_auth_account;
 use crate::terminal::{print_serialized, DisplayOptions};
 
@@ -32,13 +32,13 @@ pub enum AuthCommand {
     /// Get the account data for the user's auth account.
     Account {
         /// Base-58 public key of the account.
-        #[clap(value_parser)]
+        #[clap(value_parser = parse_address)]
         address: Option<Pubkey>,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
         json: bool,
         /// Base-58 public key of the account owner.
-        #[clap(long, value_parser, conflicts_with = "address")]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", conflicts_with = "address")]
         owner: Option<Pubkey>,
         /// Formatted data output.
         #[clap(long, value_parser)]
@@ -49,15 +49,19 @@ pub enum AuthCommand {
     /// Derive the public key of an auth account.
     Derive {
         /// Base-58 override of the account owner.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         owner: Option<Pubkey>,
     },
 }
 
 /// The main entry point and handler for all auth
 /// program interaction commands.
-pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &AuthCommand) -> Result<()> {
-    let cfg = Config::new(overrides, *program_id)?;
+pub fn entry(
+    overrides: &Overrides,
+    program_id: &Option<Pubkey>,
+    subcmd: &AuthCommand,
+) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::Auth, *program_id)?;
     match subcmd {
         AuthCommand::Account {
             address,
@@ -68,7 +72,7 @@ pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &AuthCommand) -
             &cfg,
             address,
             owner,
-            DisplayOptions::from_args(*json, *pretty),
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
         ),
         AuthCommand::CreateAccount {} => process_create_account(&cfg),
         AuthCommand::Derive { owner } => process_derive(&cfg, owner),
@@ -83,9 +87,11 @@ fn process_get_account(
     owner: &Option<Pubkey>,
     display: DisplayOptions,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
-    let owner_pk = owner.unwrap_or(signer.pubkey());
-    let auth_account = address.unwrap_or(derive_auth_account(&owner_pk, &program.id()));
+    let program = create_read_only_client(cfg);
+    let auth_account = match address {
+        Some(addr) => *addr,
+        None => derive_auth_account(&cfg.owner_or_signer(owner)?, &program.id()),
+    };
     print_serialized(
         program.account::<UserAuthentication>(auth_account)?,
         &display,
@@ -95,7 +101,7 @@ fn process_get_account(
 /// The function handler for the auth subcommand that allows
 /// users to create a new authentication account for themselves.
 fn process_create_account(cfg: &Config) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
 
     // Derive the public key of the new authentication account
     // and ensure that it does not already exist
@@ -109,7 +115,7 @@ fn process_create_account(cfg: &Config) -> Result<()> {
             .request()
             .accounts(accounts::CreateUserAuthentication {
                 user: signer.pubkey(),
-                payer: signer.pubkey(),
+                payer: cfg.fee_payer.pubkey(),
                 auth,
                 system_program,
             })
@@ -125,7 +131,7 @@ fn process_create_account(cfg: &Config) -> Result<()> {
 
 /// The function handler to derive the public key of a `jet_auth::UserAuthentication` program account.
 fn process_derive(cfg: &Config, owner: &Option<Pubkey>) -> Result<()> {
-    let acc_owner = owner.unwrap_or(cfg.keypair.pubkey());
+    let acc_owner = cfg.owner_or_signer(owner)?;
     let pk = derive_auth_account(&acc_owner, &cfg.program_id);
     println!("{}", pk);
     Ok(())
@@ -0,0 +1,229 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use clap::{Command, CommandFactory};
+use clap_complete::{generate, Shell};
+use std::collections::BTreeSet;
+
+use super::staking::resolve_stake_pool;
+use crate::config::{Config, JetProgram, Overrides};
+use crate::pubkey::{derive_auth_account, derive_margin_account, derive_stake_account};
+use crate::Opts;
+
+/// The name of the binary that completions are generated for.
+pub const BIN_NAME: &str = "jet";
+
+/// The value names of the options that are completed with address candidates.
+const ADDRESS_VALUE_NAMES: [&str; 2] = ["MINT", "PUBKEY"];
+
+/// The number of margin account seeds of the signer derived as completion candidates.
+const MARGIN_SEED_CANDIDATES: u16 = 4;
+
+/// The main entry point and handler for generating shell completion scripts
+/// or listing the address candidates used by their dynamic completions.
+pub fn entry(overrides: &Overrides, shell: &Option<Shell>, addresses: bool) -> Result<()> {
+    if addresses {
+        return process_addresses(overrides);
+    }
+
+    let shell = shell.ok_or_else(|| anyhow!("a shell is required to generate completions"))?;
+    print!("{}", completion_script(shell)?);
+
+    Ok(())
+}
+
+/// The function handler to print the address labels, token symbols and derived
+/// accounts of the signer that complete public key and token mint arguments.
+///
+/// Accounts are only derived if the signer can be loaded without interaction,
+/// so that completing an argument never prompts or waits on a hardware wallet.
+fn process_addresses(overrides: &Overrides) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::Margin, None)?;
+
+    let mut candidates: BTreeSet<String> = cfg.address_labels.values().cloned().collect();
+    candidates.extend(cfg.labels.keys().cloned());
+    candidates.extend(cfg.tokens.iter().map(|t| t.symbol.clone()));
+
+    if !cfg.keypair.is_interactive() {
+        if let Ok(owner) = cfg.signer_pubkey() {
+            candidates.insert(owner.to_string());
+            candidates.extend(derived_accounts(&cfg, &owner).iter().map(Pubkey::to_string));
+        }
+    }
+
+    candidates.iter().for_each(|c| println!("{}", c));
+
+    Ok(())
+}
+
+/// Find the long names of all options in the command tree that take a public key or token mint.
+fn address_options(cmd: &Command) -> BTreeSet<String> {
+    let mut options: BTreeSet<String> = cmd
+        .get_arguments()
+        .filter(|arg| {
+            arg.get_value_names().map_or(false, |names| {
+                names.iter().any(|n| ADDRESS_VALUE_NAMES.contains(n))
+            })
+        })
+        .filter_map(|arg| arg.get_long().map(|l| format!("--{}", l)))
+        .collect();
+
+    for sub in cmd.get_subcommands() {
+        options.extend(address_options(sub));
+    }
+
+    options
+}
+
+/// Generate the completion script for the argued shell from the `clap` definitions,
+/// extended with the dynamic completion of address options for bash, fish and zsh.
+fn completion_script(shell: Shell) -> Result<String> {
+    let mut cmd = Opts::command();
+    let mut buf = Vec::new();
+    generate(shell, &mut cmd, BIN_NAME, &mut buf);
+
+    let script = String::from_utf8(buf)?;
+    let options = address_options(&cmd);
+
+    Ok(match shell {
+        Shell::Bash => script + &bash_address_completion(&options),
+        Shell::Fish => script + &fish_address_completion(&options),
+        Shell::Zsh => zsh_address_completion(&script),
+        _ => script,
+    })
+}
+
+/// Derive the authentication account, the stake account of the default
+/// stake pool and the first margin accounts of the argued owner.
+fn derived_accounts(cfg: &Config, owner: &Pubkey) -> Vec<Pubkey> {
+    let mut accounts = vec![
+        derive_auth_account(owner, &cfg.programs.get(JetProgram::Auth)),
+        derive_stake_account(
+            &resolve_stake_pool(cfg, &None),
+            owner,
+            &cfg.programs.get(JetProgram::Staking),
+        ),
+    ];
+
+    accounts.extend(
+        (0..MARGIN_SEED_CANDIDATES)
+            .map(|seed| derive_margin_account(owner, seed, &cfg.programs.get(JetProgram::Margin))),
+    );
+
+    accounts
+}
+
+/// Build the bash function that completes the values of the address options and
+/// otherwise delegates to the generated completion function, replacing its registration.
+fn bash_address_completion(options: &BTreeSet<String>) -> String {
+    format!(
+        r#"
+_{bin}_addresses() {{
+    case "${{COMP_WORDS[COMP_CWORD-1]}}" in
+        {options})
+            local IFS=$'\n'
+            COMPREPLY=($(compgen -W "$({bin} completions --addresses 2>/dev/null)" -- "${{COMP_WORDS[COMP_CWORD]}}"))
+            return 0
+            ;;
+    esac
+    _{bin} "$@"
+}}
+
+complete -F _{bin}_addresses -o bashdefault -o default {bin}
+"#,
+        bin = BIN_NAME,
+        options = options.iter().cloned().collect::<Vec<_>>().join("|"),
+    )
+}
+
+/// Build the fish completions that add the address candidates to the address options.
+fn fish_address_completion(options: &BTreeSet<String>) -> String {
+    options
+        .iter()
+        .map(|opt| {
+            format!(
+                "complete -c {bin} -l {long} -f -a '({bin} completions --addresses 2>/dev/null)'\n",
+                bin = BIN_NAME,
+                long = opt.trim_start_matches("--"),
+            )
+        })
+        .collect()
+}
+
+/// Replace the default value completion of the address options in the generated
+/// zsh script with a function that completes the address candidates.
+fn zsh_address_completion(script: &str) -> String {
+    let function = format!(
+        r#"
+_{bin}_addresses() {{
+    local -a addresses
+    addresses=("${{(@f)$({bin} completions --addresses 2>/dev/null)}}")
+    compadd -a addresses
+}}
+"#,
+        bin = BIN_NAME,
+    );
+
+    let mut script = ADDRESS_VALUE_NAMES
+        .iter()
+        .fold(script.to_owned(), |s, name| {
+            s.replace(
+                &format!(":{}:_default'", name),
+                &format!(":{}:_{}_addresses'", name, BIN_NAME),
+            )
+        });
+
+    // The function is defined after the `#compdef` header line of the script
+    let header_end = script.find('\n').map_or(0, |i| i + 1);
+    script.insert_str(header_end, &function);
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_address_options() {
+        let options = address_options(&Opts::command());
+        assert!(options.contains("--owner"));
+        assert!(options.contains("--token-mint"));
+        assert!(options.contains("--account"));
+        assert!(!options.contains("--keypair"));
+
+        let bash = bash_address_completion(&options);
+        assert!(bash.contains("--owner|"));
+        assert!(fish_address_completion(&options).contains("complete -c jet -l owner -f"));
+    }
+
+    #[test]
+    fn zsh_address_values() {
+        let script = zsh_address_completion(
+            "#compdef jet\n'--owner=[Owner]:PUBKEY:_default' \\\n'--url=[Url]:URL:_default' \\\n",
+        );
+        assert!(script.starts_with("#compdef jet\n\n_jet_addresses() {"));
+        assert!(script.contains("'--owner=[Owner]:PUBKEY:_jet_addresses'"));
+        assert!(script.contains("'--url=[Url]:URL:_default'"));
+    }
+
+    #[test]
+    fn derive_owner_accounts() {
+        let cfg = Config::default();
+        let accounts = derived_accounts(&cfg, &Pubkey::default());
+        assert_eq!(accounts.len(), 2 + MARGIN_SEED_CANDIDATES as usize);
+    }
+}
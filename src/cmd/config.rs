@@ -0,0 +1,167 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+
+use crate::config::{JetConfigFile, Overrides, JET_CONFIG_FILE};
+use crate::terminal::{print_serialized, print_table, DisplayOptions};
+
+/// Jet configuration file based subcommand enum variants.
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Get a value, or all values, of the `--profile` or active profile.
+    Get {
+        /// The profile key (e.g. `url` or `programs.jet_margin`).
+        #[clap(value_parser)]
+        key: Option<String>,
+        /// Output data as serialized JSON.
+        #[clap(long, value_parser)]
+        json: bool,
+        /// Formatted data output.
+        #[clap(long, value_parser)]
+        pretty: bool,
+    },
+    /// List all profiles in the configuration file.
+    List,
+    /// Set a value of the `--profile` or active profile, creating the profile if needed.
+    Set {
+        /// The profile key (e.g. `url` or `programs.jet_margin`).
+        #[clap(value_parser)]
+        key: String,
+        /// The value to set for the key.
+        #[clap(value_parser)]
+        value: String,
+    },
+    /// Set the active profile used when `--profile` is not provided.
+    Use {
+        /// The name of the profile.
+        #[clap(value_parser)]
+        name: String,
+    },
+}
+
+/// The main entry point and handler for all Jet
+/// configuration file management commands.
+pub fn entry(overrides: &Overrides, subcmd: &ConfigCommand) -> Result<()> {
+    match subcmd {
+        ConfigCommand::Get { key, json, pretty } => {
+            process_get(overrides, key, DisplayOptions::from_args(*json, *pretty))
+        }
+        ConfigCommand::List => process_list(),
+        ConfigCommand::Set { key, value } => process_set(overrides, key, value),
+        ConfigCommand::Use { name } => process_use(name),
+    }
+}
+
+/// The function handler to display the value of a single
+/// key or all values of the selected configuration profile.
+fn process_get(overrides: &Overrides, key: &Option<String>, display: DisplayOptions) -> Result<()> {
+    let file = JetConfigFile::load(&JetConfigFile::path())?;
+    let profile = file
+        .profile(overrides.profile())?
+        .ok_or_else(no_profile_error)?;
+
+    match key {
+        Some(k) => {
+            let value = profile
+                .get(k)?
+                .ok_or_else(|| anyhow!("`{}` is not set in the profile", k))?;
+            println!("{}", value);
+            Ok(())
+        }
+        None => print_serialized(profile, &display),
+    }
+}
+
+/// The function handler to display the profiles of the configuration file.
+fn process_list() -> Result<()> {
+    let file = JetConfigFile::load(&JetConfigFile::path())?;
+
+    if file.profiles.is_empty() {
+        println!("No profiles found in {}", JET_CONFIG_FILE);
+        return Ok(());
+    }
+
+    let rows = file
+        .profiles
+        .iter()
+        .map(|(name, profile)| {
+            vec![
+                name.clone(),
+                if file.active_profile.as_ref() == Some(name) {
+                    "*".into()
+                } else {
+                    String::new()
+                },
+                profile.url.clone().unwrap_or_default(),
+                profile.keypair.clone().unwrap_or_default(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    print_table(&["Profile", "Active", "URL", "Keypair"], &rows);
+
+    Ok(())
+}
+
+/// The function handler to validate and set a value of the selected configuration profile.
+fn process_set(overrides: &Overrides, key: &str, value: &str) -> Result<()> {
+    let path = JetConfigFile::path();
+    let mut file = JetConfigFile::load(&path)?;
+
+    let name = overrides
+        .profile()
+        .or(file.active_profile.as_deref())
+        .ok_or_else(no_profile_error)?
+        .to_owned();
+
+    file.profiles
+        .entry(name.clone())
+        .or_default()
+        .set(key, value)?;
+
+    if file.active_profile.is_none() {
+        file.active_profile = Some(name);
+    }
+
+    file.save(&path)
+}
+
+/// The function handler to set the active profile of the configuration file.
+fn process_use(name: &str) -> Result<()> {
+    let path = JetConfigFile::path();
+    let mut file = JetConfigFile::load(&path)?;
+
+    if !file.profiles.contains_key(name) {
+        return Err(anyhow!(
+            "profile `{}` does not exist, create it with `jet config set --profile {} <KEY> <VALUE>`",
+            name,
+            name
+        ));
+    }
+
+    file.active_profile = Some(name.to_owned());
+    file.save(&path)?;
+
+    println!("Active profile: {}", name);
+
+    Ok(())
+}
+
+/// Create the error for when neither `--profile` was provided nor an active profile is set.
+fn no_profile_error() -> anyhow::Error {
+    anyhow!("no profile selected, provide `--profile <NAME>` or set one with `jet config use`")
+}
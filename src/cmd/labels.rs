@@ -0,0 +1,129 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use std::str::FromStr;
+
+use crate::config::{load_solana_config, JetConfigFile, JET_CONFIG_FILE};
+use crate::terminal::print_table;
+
+/// Address book subcommand enum variants for labeling public keys.
+#[derive(Debug, Subcommand)]
+pub enum LabelsCommand {
+    /// Add a label for a public key to the address book.
+    Add {
+        /// The label to use in place of the public key.
+        #[clap(value_parser)]
+        label: String,
+        /// The base-58 public key to label.
+        #[clap(value_parser)]
+        address: Pubkey,
+    },
+    /// List the labels of the address book and the Solana CLI configuration.
+    List,
+    /// Remove a label from the address book.
+    Remove {
+        /// The label to remove.
+        #[clap(value_parser)]
+        label: String,
+    },
+}
+
+/// The main entry point and handler for all address book commands.
+pub fn entry(subcmd: &LabelsCommand) -> Result<()> {
+    match subcmd {
+        LabelsCommand::Add { label, address } => process_add(label, address),
+        LabelsCommand::List => process_list(),
+        LabelsCommand::Remove { label } => process_remove(label),
+    }
+}
+
+/// The function handler to add a new label for a public key to the address book.
+fn process_add(label: &str, address: &Pubkey) -> Result<()> {
+    if Pubkey::from_str(label).is_ok() {
+        return Err(anyhow!(
+            "`{}` is a public key and can not be a label",
+            label
+        ));
+    }
+
+    let path = JetConfigFile::path();
+    let mut file = JetConfigFile::load(&path)?;
+
+    if let Some(existing) = file.labels.get(label) {
+        return Err(anyhow!(
+            "`{}` is already a label for {}, remove it with `jet labels remove {}`",
+            label,
+            existing,
+            label
+        ));
+    }
+
+    file.labels.insert(label.to_owned(), address.to_string());
+    file.save(&path)?;
+
+    println!("{}: {}", label, address);
+
+    Ok(())
+}
+
+/// The function handler to display the labels of the address
+/// book followed by those of the Solana CLI configuration.
+fn process_list() -> Result<()> {
+    let file = JetConfigFile::load(&JetConfigFile::path())?;
+
+    let mut solana_labels = load_solana_config()?
+        .address_labels
+        .into_iter()
+        .collect::<Vec<_>>();
+    solana_labels.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let rows = file
+        .labels
+        .iter()
+        .map(|(label, addr)| vec![label.clone(), addr.clone(), "jet".into()])
+        .chain(
+            solana_labels
+                .into_iter()
+                .map(|(addr, label)| vec![label, addr, "solana".into()]),
+        )
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        println!("No labels found in {}", JET_CONFIG_FILE);
+        return Ok(());
+    }
+
+    print_table(&["Label", "Address", "Source"], &rows);
+
+    Ok(())
+}
+
+/// The function handler to remove a label from the address book.
+fn process_remove(label: &str) -> Result<()> {
+    let path = JetConfigFile::path();
+    let mut file = JetConfigFile::load(&path)?;
+
+    file.labels.remove(label).ok_or_else(|| {
+        anyhow!(
+            "`{}` is not a label in the address book of {}",
+            label,
+            JET_CONFIG_FILE
+        )
+    })?;
+    file.save(&path)
+}
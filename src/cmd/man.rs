@@ -0,0 +1,52 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+use std::fs::{create_dir_all, write};
+use std::io::stdout;
+use std::path::{Path, PathBuf};
+
+use super::completions::BIN_NAME;
+use crate::Opts;
+
+/// The main entry point and handler for generating the roff man pages, either
+/// printing the top-level page or writing a page for every command to a directory.
+pub fn entry(out_dir: &Option<PathBuf>) -> Result<()> {
+    let cmd = Opts::command().name(BIN_NAME);
+
+    match out_dir {
+        Some(dir) => {
+            create_dir_all(dir)?;
+            write_pages(&cmd, dir, BIN_NAME)
+        }
+        None => Ok(Man::new(cmd).render(&mut stdout())?),
+    }
+}
+
+/// Write the man page of the argued command and, recursively, each of its subcommands
+/// to the directory with their page names joined by dashes (e.g. `jet-margin-pool.1`).
+fn write_pages(cmd: &Command, dir: &Path, name: &str) -> Result<()> {
+    let mut buf = Vec::new();
+    Man::new(cmd.clone().name(name)).render(&mut buf)?;
+    write(dir.join(format!("{}.1", name)), buf)?;
+
+    for sub in cmd.get_subcommands() {
+        write_pages(sub, dir, &format!("{}-{}", name, sub.get_name()))?;
+    }
+
+    Ok(())
+}
@@ -13,23 +13,43 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use anchor_client::solana_client::rpc_config::RpcSimulateTransactionConfig;
 use anchor_client::solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use anchor_client::solana_sdk::instruction::{AccountMeta, Instruction};
 use anchor_client::solana_sdk::pubkey::Pubkey;
 use anchor_client::solana_sdk::signer::Signer;
 use anchor_client::solana_sdk::system_program::ID as system_program;
 use anchor_client::solana_sdk::sysvar::rent::ID as rent;
+use anchor_client::solana_sdk::transaction::{Transaction, TransactionError};
+use anchor_client::Program;
+use anchor_spl::associated_token::get_associated_token_address;
 use anchor_spl::token::{TokenAccount, ID as token_program};
 use anyhow::{anyhow, Result};
-use clap::Subcommand;
-use jet_margin::{accounts, instruction, MarginAccount};
-use jet_metadata::PositionTokenMetadata;
+use clap::{Args, Subcommand};
+use jet_margin::{
+    accounts, instruction, AccountPosition, CompactAccountMeta, Liquidation, MarginAccount,
+};
+use jet_margin_pool::MarginPool;
+use jet_metadata::{LiquidatorMetadata, MarginAdapterMetadata, PositionTokenMetadata, TokenKind};
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::config::{Config, Overrides};
+use super::margin_pool::{margin_repay_instruction, withdraw_instruction};
+
+use crate::amount::{mint_decimals, TokenAmount, UiAmount};
+use crate::config::{parse_address, Config, JetProgram, Overrides};
 use crate::macros::{assert_exists, assert_not_exists};
-use crate::program::{create_program_client, send_with_approval};
-use crate::pubkey::derive_margin_account;
-use crate::terminal::{print_serialized, DisplayOptions};
+use crate::program::{
+    account_exists, build_instruction, create_program_client, create_read_only_client,
+    send_batches_with_approval, send_with_approval,
+};
+use crate::pubkey::{
+    derive_adapter_metadata, derive_liquidation, derive_liquidator_metadata, derive_margin_account,
+    derive_margin_pool, derive_position_token_account,
+};
+use crate::terminal::{print_serialized, print_table, DisplayOptions, Spinner};
+use crate::token::TokenMint;
 
 /// Utility struct for serialization of the health of
 /// a user's margin account for display purposes.
@@ -37,21 +57,142 @@ use crate::terminal::{print_serialized, DisplayOptions};
 struct AccountHealth {
     healthy: bool,
     liquidating: bool,
+    stale_positions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure: Option<String>,
+}
+
+/// Utility struct for serialization of a single registered
+/// position in a margin account's portfolio for display purposes.
+#[derive(Debug, Serialize)]
+struct PortfolioPosition {
+    token_mint: String,
+    #[serde(skip)]
+    token_symbol: String,
+    kind: String,
+    balance: f64,
+    price: f64,
+    value: f64,
+    collateral_weight: f64,
+    weighted_collateral: f64,
+    required_collateral: f64,
+    #[serde(skip)]
+    claim: bool,
+}
+
+impl PortfolioPosition {
+    /// Value a position from its balance and price, weighting collateral for deposits
+    /// and the collateral requirement for claims by the position's collateral weight.
+    fn new(token_mint: &Pubkey, kind: &TokenKind, balance: f64, price: f64, weight: u16) -> Self {
+        let value = balance * price;
+        let collateral_weight = f64::from(weight) / 10_000.0;
+
+        let (weighted_collateral, required_collateral) = match kind {
+            TokenKind::Collateral => (value * collateral_weight, 0.0),
+            TokenKind::Claim => (0.0, value * collateral_weight),
+            _ => (0.0, 0.0),
+        };
+
+        Self {
+            token_mint: token_mint.to_string(),
+            token_symbol: token_mint.to_string(),
+            kind: format!("{:?}", kind),
+            balance,
+            price,
+            value,
+            collateral_weight,
+            weighted_collateral,
+            required_collateral,
+            claim: matches!(kind, TokenKind::Claim),
+        }
+    }
+}
+
+/// Utility struct for serialization of the aggregate valuation of
+/// all positions in a margin account for display purposes.
+#[derive(Debug, Serialize)]
+struct Portfolio {
+    account: String,
+    positions: Vec<PortfolioPosition>,
+    equity: f64,
+    liabilities: f64,
+    weighted_collateral: f64,
+    required_collateral: f64,
+    health_ratio: Option<f64>,
+}
+
+impl Portfolio {
+    /// Aggregate the argued position valuations into the account's totals.
+    fn new(account: &Pubkey, positions: Vec<PortfolioPosition>) -> Self {
+        let (assets, liabilities) = positions.iter().fold((0.0, 0.0), |(a, l), p| {
+            if p.claim {
+                (a, l + p.value)
+            } else {
+                (a + p.value, l)
+            }
+        });
+        let weighted_collateral = positions.iter().map(|p| p.weighted_collateral).sum();
+        let required_collateral: f64 = positions.iter().map(|p| p.required_collateral).sum();
+
+        Self {
+            account: account.to_string(),
+            positions,
+            equity: assets - liabilities,
+            liabilities,
+            weighted_collateral,
+            required_collateral,
+            health_ratio: (required_collateral > 0.0)
+                .then(|| weighted_collateral / required_collateral),
+        }
+    }
+}
+
+/// Utility struct for serialization of a margin account that is
+/// a candidate for liquidation along with its collateral shortfall.
+#[derive(Debug, Serialize)]
+struct LiquidationCandidate {
+    account: String,
+    owner: String,
+    equity: f64,
+    liabilities: f64,
+    weighted_collateral: f64,
+    required_collateral: f64,
+    shortfall: f64,
+    liquidating: bool,
+}
+
+impl LiquidationCandidate {
+    /// Instantiate a candidate from the aggregate valuation of a margin account's portfolio.
+    fn new(owner: &Pubkey, liquidating: bool, portfolio: &Portfolio) -> Self {
+        Self {
+            account: portfolio.account.clone(),
+            owner: owner.to_string(),
+            equity: portfolio.equity,
+            liabilities: portfolio.liabilities,
+            weighted_collateral: portfolio.weighted_collateral,
+            required_collateral: portfolio.required_collateral,
+            shortfall: (portfolio.required_collateral - portfolio.weighted_collateral).max(0.0),
+            liquidating,
+        }
+    }
 }
 
+/// The maximum number of position refresh instructions to send in a single transaction.
+const REFRESH_BATCH_SIZE: usize = 4;
+
 /// Margin program based subcommand enum variants.
 #[derive(Debug, Subcommand)]
 pub enum MarginCommand {
     /// Get the account data for a user's margin account or all they own.
     Account {
         /// Base-58 public key of the margin account.
-        #[clap(value_parser)]
+        #[clap(value_parser = parse_address)]
         address: Option<Pubkey>,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
         json: bool,
         /// Base-58 public key of the owner to use to derive.
-        #[clap(long, value_parser, conflicts_with = "address")]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", conflicts_with = "address")]
         owner: Option<Pubkey>,
         /// Formatted data output.
         #[clap(long, value_parser)]
@@ -59,67 +200,224 @@ pub enum MarginCommand {
     },
     /// Check the health of the positions in a margin account.
     Check {
-        /// Base-58 public key of the margin account.
-        #[clap(value_parser)]
-        address: Pubkey,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
         json: bool,
+        /// Simulate `jet_margin::VerifyHealthy` on-chain after refreshing position prices.
+        #[clap(long, value_parser)]
+        onchain: bool,
         /// Formatted data output.
         #[clap(long, value_parser)]
         pretty: bool,
     },
     /// Close your margin account.
     CloseAccount {
-        /// The public key to receive the rent.
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Close all registered positions with empty balances before the account.
         #[clap(long, value_parser)]
+        close_positions: bool,
+        /// The public key to receive the rent.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         receiver: Option<Pubkey>,
-        /// The numerical seed for the account to close.
-        #[clap(short, long, value_parser)]
-        seed: u16,
     },
     /// Close a position owned by a margin account.
     ClosePosition {
-        /// Base-58 public key of the margin account.
-        #[clap(long, value_parser)]
-        account: Pubkey,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
         /// Base-58 public key of the target position token mint.
-        #[clap(long, value_parser)]
-        position_mint: Pubkey,
+        #[clap(long, value_parser, value_name = "MINT")]
+        position_mint: TokenMint,
         /// The public key to receive the rent.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         receiver: Option<Pubkey>,
     },
     /// Create a new margin account.
     CreateAccount {
-        /// The numerical seed for the new account.
+        /// The numerical seed for the new account (defaults to the lowest unused seed).
         #[clap(short, long, value_parser)]
-        seed: u16,
+        seed: Option<u16>,
     },
     /// Derive the public key of a margin account.
     Derive {
         /// Base-58 override of the account owner.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         owner: Option<Pubkey>,
         /// The numerical seed for the account.
         #[clap(short, long, value_parser)]
         seed: u16,
     },
+    /// Derive the public key of the token account for a position in a margin account.
+    DerivePosition {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Base-58 public key of the target position token mint.
+        #[clap(long, value_parser, value_name = "MINT")]
+        position_mint: TokenMint,
+    },
+    /// Begin or end the liquidation of an unhealthy margin account.
+    Liquidate {
+        #[clap(subcommand)]
+        subcmd: LiquidateCommand,
+    },
+    /// Invoke a margin pool action on a margin account being liquidated.
+    LiquidatorInvoke {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        #[clap(subcommand)]
+        action: LiquidatorAction,
+    },
+    /// Display the valued positions and health of a margin account.
+    Portfolio {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Output data as serialized JSON.
+        #[clap(long, value_parser)]
+        json: bool,
+        /// Formatted data output.
+        #[clap(long, value_parser)]
+        pretty: bool,
+    },
+    /// Refresh the prices and metadata of all positions in a margin account.
+    Refresh {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+    },
     /// Register a new margin position.
     Register {
-        /// Base-58 public key of the margin account.
-        #[clap(long, value_parser)]
-        account: Pubkey,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
         /// Base-58 public key of the target position token mint.
+        #[clap(long, value_parser, value_name = "MINT")]
+        position_mint: TokenMint,
+    },
+    /// List margin accounts that are candidates for liquidation ranked by shortfall.
+    Unhealthy {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Output data as serialized JSON.
+        #[clap(long, value_parser)]
+        json: bool,
+        /// Formatted data output.
+        #[clap(long, value_parser)]
+        pretty: bool,
+        /// Scan all existing margin accounts.
+        #[clap(long, value_parser, conflicts_with_all = &["account", "seed"])]
+        scan: bool,
+    },
+}
+
+/// Margin account liquidation subcommand enum variants.
+#[derive(Debug, Subcommand)]
+pub enum LiquidateCommand {
+    /// Begin the liquidation of an unhealthy margin account as a registered liquidator.
+    Begin {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+    },
+    /// End the liquidation of a margin account.
+    End {
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Base-58 override of the liquidator that began the liquidation.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        liquidator: Option<Pubkey>,
+    },
+}
+
+/// Margin pool actions a liquidator can invoke on a margin account being liquidated.
+#[derive(Debug, Subcommand)]
+pub enum LiquidatorAction {
+    /// Repay a loan of the margin account with its deposits in the same pool.
+    Repay {
+        /// The maximum token amount to repay to the pool (e.g. `1.5`, `1.5 USDC` or `max`).
+        #[clap(value_parser, required_unless_present = "all")]
+        amount: Option<TokenAmount>,
+        /// Repay the full outstanding loan balance.
+        #[clap(long, value_parser, conflicts_with = "amount")]
+        all: bool,
+        /// Interpret the amount as a raw value in the mint's base units.
+        #[clap(long, value_parser)]
+        base_units: bool,
+        /// Token mint to derive margin pool.
+        #[clap(long, value_parser, value_name = "MINT")]
+        token_mint: TokenMint,
+    },
+    /// Withdraw collateral deposits of the margin account.
+    Withdraw {
+        /// The token amount to withdraw from the pool (e.g. `1.5`, `1.5 USDC` or `max`).
+        #[clap(value_parser, required_unless_present = "all")]
+        amount: Option<TokenAmount>,
+        /// Withdraw the full deposit note balance.
+        #[clap(long, value_parser, conflicts_with = "amount")]
+        all: bool,
+        /// Interpret the amount as a raw value in the mint's base units.
         #[clap(long, value_parser)]
-        position_mint: Pubkey,
+        base_units: bool,
+        /// Token account to receive the withdrawn funds (defaults to your associated token account).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        destination: Option<Pubkey>,
+        /// Token mint to derive margin pool.
+        #[clap(long, value_parser, value_name = "MINT")]
+        token_mint: TokenMint,
     },
 }
 
+/// Common arguments to select a margin account by its public key or address label,
+/// either positionally or through `--account`, or by deriving it from the owner and seed.
+#[derive(Args, Debug)]
+pub struct MarginAccountSelector {
+    /// Base-58 public key or address label of the margin account.
+    #[clap(long, value_parser = parse_address, value_name = "PUBKEY", conflicts_with = "seed")]
+    account: Option<Pubkey>,
+    /// Base-58 public key or address label of the margin account, as an alternative to `--account`.
+    #[clap(value_parser = parse_address, conflicts_with_all = &["account", "seed"])]
+    address: Option<Pubkey>,
+    /// Base-58 override of the account owner to derive with.
+    #[clap(long, value_parser = parse_address, value_name = "PUBKEY", requires = "seed")]
+    owner: Option<Pubkey>,
+    /// The numerical seed of the margin account to derive.
+    #[clap(short, long, value_parser)]
+    seed: Option<u16>,
+}
+
+impl MarginAccountSelector {
+    /// Find the public key of the selected margin account if one was selected, where
+    /// a derived account's owner defaults to the signer of the configuration.
+    pub fn find(&self, cfg: &Config) -> Result<Option<Pubkey>> {
+        if let Some(acc) = self.account.or(self.address) {
+            return Ok(Some(acc));
+        }
+
+        self.seed
+            .map(|seed| {
+                Ok(derive_margin_account(
+                    &cfg.owner_or_signer(&self.owner)?,
+                    seed,
+                    &cfg.programs.get(JetProgram::Margin),
+                ))
+            })
+            .transpose()
+    }
+
+    /// Resolve the public key of the selected margin account or
+    /// return an error if no account was selected.
+    pub fn resolve(&self, cfg: &Config) -> Result<Pubkey> {
+        self.find(cfg)?
+            .ok_or_else(|| anyhow!("a margin account is required through `--account` or `--seed`"))
+    }
+}
+
 /// The main entry point and handler for all margin
 /// program interaction commands.
-pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &MarginCommand) -> Result<()> {
-    let cfg = Config::new(overrides, *program_id)?;
+pub fn entry(
+    overrides: &Overrides,
+    program_id: &Option<Pubkey>,
+    subcmd: &MarginCommand,
+) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::Margin, *program_id)?;
     match subcmd {
         MarginCommand::Account {
             address,
@@ -130,27 +428,85 @@ pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &MarginCommand)
             &cfg,
             address,
             owner,
-            DisplayOptions::from_args(*json, *pretty),
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
         ),
         MarginCommand::Check {
-            address,
+            account,
             json,
+            onchain,
             pretty,
-        } => process_check_health(&cfg, address, DisplayOptions::from_args(*json, *pretty)),
-        MarginCommand::CloseAccount { receiver, seed } => {
-            process_close_account(&cfg, receiver, *seed)
-        }
+        } => process_check_health(
+            &cfg,
+            &account.resolve(&cfg)?,
+            *onchain,
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
+        MarginCommand::CloseAccount {
+            account,
+            close_positions,
+            receiver,
+        } => process_close_account(&cfg, &account.resolve(&cfg)?, *close_positions, receiver),
         MarginCommand::ClosePosition {
             account,
             position_mint,
             receiver,
-        } => process_close_position(&cfg, account, position_mint, receiver),
-        MarginCommand::CreateAccount { seed } => process_create_account(&cfg, *seed),
+        } => process_close_position(
+            &cfg,
+            &account.resolve(&cfg)?,
+            &cfg.tokens.resolve(position_mint)?,
+            receiver,
+        ),
+        MarginCommand::CreateAccount { seed } => process_create_account(&cfg, seed),
         MarginCommand::Derive { owner, seed } => process_derive(&cfg, owner, *seed),
+        MarginCommand::DerivePosition {
+            account,
+            position_mint,
+        } => process_derive_position(
+            &cfg,
+            &account.resolve(&cfg)?,
+            &cfg.tokens.resolve(position_mint)?,
+        ),
+        MarginCommand::Liquidate { subcmd } => match subcmd {
+            LiquidateCommand::Begin { account } => {
+                process_liquidate_begin(&cfg, &account.resolve(&cfg)?)
+            }
+            LiquidateCommand::End {
+                account,
+                liquidator,
+            } => process_liquidate_end(&cfg, &account.resolve(&cfg)?, liquidator),
+        },
+        MarginCommand::LiquidatorInvoke { account, action } => {
+            process_liquidator_invoke(&cfg, &account.resolve(&cfg)?, action)
+        }
+        MarginCommand::Portfolio {
+            account,
+            json,
+            pretty,
+        } => process_portfolio(
+            &cfg,
+            &account.resolve(&cfg)?,
+            json.then(|| DisplayOptions::from_args(*json, *pretty)),
+        ),
+        MarginCommand::Refresh { account } => process_refresh(&cfg, &account.resolve(&cfg)?),
         MarginCommand::Register {
             account,
             position_mint,
-        } => process_register(&cfg, account, position_mint),
+        } => process_register(
+            &cfg,
+            &account.resolve(&cfg)?,
+            &cfg.tokens.resolve(position_mint)?,
+        ),
+        MarginCommand::Unhealthy {
+            account,
+            json,
+            pretty,
+            scan,
+        } => process_unhealthy(
+            &cfg,
+            &account.find(&cfg)?,
+            *scan,
+            json.then(|| DisplayOptions::from_args(*json, *pretty)),
+        ),
     }
 }
 
@@ -162,22 +518,14 @@ fn process_get_account(
     owner: &Option<Pubkey>,
     display: DisplayOptions,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
-    let owner_pk = owner.unwrap_or(signer.pubkey());
+    let program = create_read_only_client(cfg);
 
     if let Some(addr) = address {
         return print_serialized(program.account::<MarginAccount>(*addr)?, &display);
     }
 
     let margins: Vec<MarginAccount> = program
-        .accounts(vec![
-            RpcFilterType::DataSize(8 + std::mem::size_of::<MarginAccount>() as u64),
-            RpcFilterType::Memcmp(Memcmp {
-                offset: 16,
-                bytes: MemcmpEncodedBytes::Bytes(owner_pk.to_bytes().to_vec()),
-                encoding: None,
-            }),
-        ])?
+        .accounts(owned_margin_account_filters(&cfg.owner_or_signer(owner)?))?
         .iter()
         .map(|acc| acc.1)
         .collect();
@@ -185,46 +533,160 @@ fn process_get_account(
     print_serialized(margins, &display)
 }
 
-/// The function handler to verify the health of the positions in a margin
-/// account via the `jet_margin::VerifyHealthy` transaction.
-fn process_check_health(cfg: &Config, address: &Pubkey, display: DisplayOptions) -> Result<()> {
-    let (program, _) = create_program_client(cfg);
+/// The function handler to verify the health of the positions in a margin account
+/// and report those with stale prices, either locally from the account data or by
+/// simulating the `jet_margin::VerifyHealthy` transaction after refreshing prices.
+fn process_check_health(
+    cfg: &Config,
+    address: &Pubkey,
+    onchain: bool,
+    display: DisplayOptions,
+) -> Result<()> {
+    let program = create_read_only_client(cfg);
 
     let acc = program.account::<MarginAccount>(*address)?;
-    let healthy = acc.verify_healthy_positions().is_ok();
     let liquidating = acc.verify_not_liquidating().is_err();
+    let stale_positions = find_stale_positions(&acc)?
+        .iter()
+        .map(|pos| pos.token.to_string())
+        .collect();
+
+    if !onchain {
+        return print_serialized(
+            AccountHealth {
+                healthy: acc.verify_healthy_positions().is_ok(),
+                liquidating,
+                stale_positions,
+                failure: None,
+            },
+            &display,
+        );
+    }
+
+    // Refresh the prices of all positions prior to verifying the
+    // account health in the same simulated transaction
+    let sp = Spinner::new("Simulating health verification");
+    let mut ixs = refresh_position_instructions(cfg, &acc, address)?;
+    ixs.push(build_instruction(
+        &program.id(),
+        accounts::VerifyHealthy {
+            margin_account: *address,
+        },
+        instruction::VerifyHealthy {},
+    ));
+
+    // The unsigned transaction is paid for by the account owner and only
+    // simulated, so health can be checked without access to any signer
+    let tx = Transaction::new_with_payer(&ixs, Some(&acc.owner));
+    let result = program
+        .rpc()
+        .simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                replace_recent_blockhash: true,
+                sig_verify: false,
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )?
+        .value;
+    sp.finish_with_message("Simulation complete");
+
+    // Only a failure of the final `VerifyHealthy` instruction means the account is
+    // unhealthy, while any other failure is an error of the simulation itself
+    let logs = result.logs.unwrap_or_default();
+    let failure = match result.err {
+        None => None,
+        Some(TransactionError::InstructionError(index, err)) if index as usize == ixs.len() - 1 => {
+            Some(anchor_error_log(&logs).unwrap_or_else(|| err.to_string()))
+        }
+        Some(err) => {
+            return Err(anyhow!(
+                "failed to simulate the health verification: {}",
+                anchor_error_log(&logs).unwrap_or_else(|| err.to_string())
+            ))
+        }
+    };
 
     print_serialized(
         AccountHealth {
-            healthy,
+            healthy: failure.is_none(),
             liquidating,
+            stale_positions,
+            failure,
         },
         &display,
     )
 }
 
 /// The function handler to allow users to close their margin account and receive back rent.
-fn process_close_account(cfg: &Config, receiver: &Option<Pubkey>, seed: u16) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+fn process_close_account(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    close_positions: bool,
+    receiver: &Option<Pubkey>,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
     let rent_receiver = receiver.unwrap_or(signer.pubkey());
+    let mut req = program.request();
+    let mut ix_names = Vec::<&str>::new();
 
-    // Derive the target margin account and assert that it exists on-chain
-    let margin_account = derive_margin_account(&signer.pubkey(), seed, &program.id());
-    assert_exists!(&program, MarginAccount, &margin_account);
+    assert_exists!(&program, MarginAccount, margin_account);
+
+    // Close each registered position ahead of the account itself
+    // if they all have empty balances, otherwise refuse and report them
+    if close_positions {
+        let account = program.account::<MarginAccount>(*margin_account)?;
+
+        let mut non_empty = Vec::new();
+        for pos in account.positions() {
+            let amount = program.account::<TokenAccount>(pos.address)?.amount;
+            if amount > 0 {
+                non_empty.push(format!(
+                    "{}: {}",
+                    cfg.tokens.symbol_or_mint(&pos.token),
+                    UiAmount::new(amount, mint_decimals(&program, &pos.token)?)
+                ));
+            }
+        }
+
+        if !non_empty.is_empty() {
+            return Err(anyhow!(
+                "margin account {} has positions with non-zero balances:\n  {}",
+                margin_account,
+                non_empty.join("\n  ")
+            ));
+        }
+
+        for pos in account.positions() {
+            req = req.instruction(build_instruction(
+                &program.id(),
+                accounts::ClosePosition {
+                    authority: signer.pubkey(),
+                    receiver: rent_receiver,
+                    margin_account: *margin_account,
+                    position_token_mint: pos.token,
+                    token_account: pos.address,
+                    token_program,
+                },
+                instruction::ClosePosition {},
+            ));
+            ix_names.push("jet_margin::ClosePosition");
+        }
+    }
+
+    ix_names.push("jet_margin::CloseAccount");
 
     // Build and send `jet_margin::CloseAccount` transaction
     send_with_approval(
         cfg,
-        program
-            .request()
-            .accounts(accounts::CloseAccount {
-                owner: signer.pubkey(),
-                receiver: rent_receiver,
-                margin_account,
-            })
-            .args(instruction::CloseAccount {})
-            .signer(signer.as_ref()),
-        vec!["jet_margin::CloseAccount"],
+        req.accounts(accounts::CloseAccount {
+            owner: signer.pubkey(),
+            receiver: rent_receiver,
+            margin_account: *margin_account,
+        })
+        .args(instruction::CloseAccount {})
+        .signer(signer.as_ref()),
+        ix_names,
     )
 }
 
@@ -235,15 +697,10 @@ fn process_close_position(
     position_mint: &Pubkey,
     receiver: &Option<Pubkey>,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
     let rent_receiver = receiver.unwrap_or(signer.pubkey());
 
-    let token_account = Pubkey::find_program_address(
-        &[margin_account.as_ref(), position_mint.as_ref()],
-        &token_program,
-    )
-    .0;
-
+    let token_account = find_position_token_account(cfg, margin_account, position_mint);
     assert_exists!(&program, TokenAccount, &token_account);
 
     send_with_approval(
@@ -266,9 +723,19 @@ fn process_close_position(
     Ok(())
 }
 
-/// The function handler for a user to create a new margin account for themselves.
-fn process_create_account(cfg: &Config, seed: u16) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+/// The function handler for a user to create a new margin account for themselves,
+/// using the lowest seed not taken by their existing accounts if one wasn't provided.
+fn process_create_account(cfg: &Config, seed: &Option<u16>) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    let seed = match seed {
+        Some(s) => *s,
+        None => {
+            let used = find_used_seeds(&program, &signer.pubkey())?;
+            lowest_unused_seed(&used)
+                .ok_or_else(|| anyhow!("all margin account seeds are in use"))?
+        }
+    };
 
     // Derive the public key for the margin account and assert that is does not already exist
     let margin_account = derive_margin_account(&signer.pubkey(), seed, &program.id());
@@ -281,7 +748,7 @@ fn process_create_account(cfg: &Config, seed: u16) -> Result<()> {
             .request()
             .accounts(accounts::CreateAccount {
                 owner: signer.pubkey(),
-                payer: signer.pubkey(),
+                payer: cfg.fee_payer.pubkey(),
                 margin_account,
                 system_program,
             })
@@ -290,6 +757,7 @@ fn process_create_account(cfg: &Config, seed: u16) -> Result<()> {
         vec!["jet_margin::CreateAccount"],
     )?;
 
+    println!("Seed: {}", seed);
     println!("Pubkey: {}", margin_account);
 
     Ok(())
@@ -297,25 +765,745 @@ fn process_create_account(cfg: &Config, seed: u16) -> Result<()> {
 
 /// The function handler to derive the public key of a `jet_margin::MarginAccount`.
 fn process_derive(cfg: &Config, owner: &Option<Pubkey>, seed: u16) -> Result<()> {
-    let acc_owner = owner.unwrap_or(cfg.keypair.pubkey());
+    let acc_owner = cfg.owner_or_signer(owner)?;
     let pk = derive_margin_account(&acc_owner, seed, &cfg.program_id);
     println!("{}", pk);
     Ok(())
 }
 
+/// The function handler to derive the public key of the token account
+/// that holds the balance of a position in a `jet_margin::MarginAccount`.
+fn process_derive_position(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    position_mint: &Pubkey,
+) -> Result<()> {
+    let pk = derive_position_token_account(margin_account, position_mint, &cfg.program_id);
+    println!("{}", pk);
+    Ok(())
+}
+
+/// The function handler for a registered liquidator to begin the liquidation of an unhealthy
+/// margin account through `jet_margin::LiquidateBegin`, taking control of the account.
+fn process_liquidate_begin(cfg: &Config, margin_account: &Pubkey) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    assert_exists!(&program, MarginAccount, margin_account);
+
+    // The liquidator must be registered with the metadata program
+    let liquidator_metadata =
+        derive_liquidator_metadata(&signer.pubkey(), &cfg.programs.get(JetProgram::Metadata));
+    assert_exists!(&program, LiquidatorMetadata, &liquidator_metadata);
+
+    let liquidation = derive_liquidation(margin_account, &signer.pubkey(), &program.id());
+    assert_not_exists!(&program, Liquidation, &liquidation);
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .accounts(accounts::LiquidateBegin {
+                margin_account: *margin_account,
+                payer: cfg.fee_payer.pubkey(),
+                liquidator: signer.pubkey(),
+                liquidator_metadata,
+                liquidation,
+                system_program,
+            })
+            .args(instruction::LiquidateBegin {})
+            .signer(signer.as_ref()),
+        vec!["jet_margin::LiquidateBegin"],
+    )?;
+
+    println!("Liquidation: {}", liquidation);
+
+    Ok(())
+}
+
+/// The function handler to end the liquidation of a margin account
+/// through `jet_margin::LiquidateEnd` and receive back the rent.
+fn process_liquidate_end(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    liquidator: &Option<Pubkey>,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    let liquidation = derive_liquidation(
+        margin_account,
+        &liquidator.unwrap_or(signer.pubkey()),
+        &program.id(),
+    );
+    assert_exists!(&program, Liquidation, &liquidation);
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .accounts(accounts::LiquidateEnd {
+                authority: signer.pubkey(),
+                margin_account: *margin_account,
+                liquidation,
+            })
+            .args(instruction::LiquidateEnd {})
+            .signer(signer.as_ref()),
+        vec!["jet_margin::LiquidateEnd"],
+    )
+}
+
+/// The function handler for a liquidator to repay the loans or withdraw the collateral of
+/// a margin account they are liquidating through `jet_margin::LiquidatorInvoke`.
+fn process_liquidator_invoke(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    action: &LiquidatorAction,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+    let pool_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::MarginPool)));
+
+    let liquidation = derive_liquidation(margin_account, &signer.pubkey(), &program.id());
+    assert_exists!(&program, Liquidation, &liquidation);
+
+    let (pool_ix, name) = match action {
+        LiquidatorAction::Repay {
+            amount,
+            all: _,
+            base_units,
+            token_mint,
+        } => {
+            let margin_pool =
+                derive_margin_pool(&cfg.tokens.resolve(token_mint)?, &pool_program.id());
+            assert_exists!(&program, MarginPool, &margin_pool);
+
+            let pool = pool_program.account::<MarginPool>(margin_pool)?;
+            margin_repay_instruction(
+                cfg,
+                &pool_program,
+                &amount.clone().unwrap_or(TokenAmount::Max),
+                *base_units,
+                margin_account,
+                &find_position_token_account(cfg, margin_account, &pool.deposit_note_mint),
+                &find_position_token_account(cfg, margin_account, &pool.loan_note_mint),
+                &margin_pool,
+                &pool,
+            )?
+        }
+        LiquidatorAction::Withdraw {
+            amount,
+            all: _,
+            base_units,
+            destination,
+            token_mint,
+        } => {
+            let margin_pool =
+                derive_margin_pool(&cfg.tokens.resolve(token_mint)?, &pool_program.id());
+            assert_exists!(&program, MarginPool, &margin_pool);
+
+            let pool = pool_program.account::<MarginPool>(margin_pool)?;
+            withdraw_instruction(
+                cfg,
+                &pool_program,
+                &amount.clone().unwrap_or(TokenAmount::Max),
+                *base_units,
+                margin_account,
+                &destination.unwrap_or_else(|| {
+                    get_associated_token_address(&signer.pubkey(), &pool.token_mint)
+                }),
+                &margin_pool,
+                &pool,
+                &find_position_token_account(cfg, margin_account, &pool.deposit_note_mint),
+            )?
+        }
+    };
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .instruction(liquidator_invoke_instruction(
+                cfg,
+                &signer.pubkey(),
+                margin_account,
+                pool_ix,
+            )?)
+            .signer(signer.as_ref()),
+        vec![format!("jet_margin::LiquidatorInvoke -> {}", name).as_str()],
+    )
+}
+
+/// The function handler to value each registered position in a margin account from its
+/// token account balance, price and `jet_metadata::PositionTokenMetadata` collateral
+/// weight and display them along with the account's aggregate equity and health.
+fn process_portfolio(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    display: Option<DisplayOptions>,
+) -> Result<()> {
+    let program = create_read_only_client(cfg);
+    let metadata_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::Metadata)));
+
+    let sp = Spinner::new("Fetching margin account positions");
+    let account = program.account::<MarginAccount>(*margin_account)?;
+    let positions = value_positions(
+        cfg,
+        &program,
+        &metadata_program,
+        &account,
+        &mut HashMap::new(),
+    )?;
+    sp.finish_with_message("Positions retrieved");
+
+    let portfolio = Portfolio::new(margin_account, positions);
+
+    if let Some(opts) = display {
+        return print_serialized(portfolio, &opts);
+    }
+
+    println!("Margin Account: {}", portfolio.account);
+    println!();
+
+    print_table(
+        &[
+            "Token",
+            "Kind",
+            "Balance",
+            "Price",
+            "Value",
+            "Collateral Weight",
+            "Required Collateral",
+        ],
+        &portfolio
+            .positions
+            .iter()
+            .map(|p| {
+                vec![
+                    p.token_symbol.clone(),
+                    p.kind.clone(),
+                    p.balance.to_string(),
+                    format!("{:.4}", p.price),
+                    format!("{:.2}", p.value),
+                    format!("{:.2}%", p.collateral_weight * 100.0),
+                    format!("{:.2}", p.required_collateral),
+                ]
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    println!();
+    println!("Equity:              {:.2}", portfolio.equity);
+    println!("Liabilities:         {:.2}", portfolio.liabilities);
+    println!("Weighted Collateral: {:.2}", portfolio.weighted_collateral);
+    println!("Required Collateral: {:.2}", portfolio.required_collateral);
+    println!(
+        "Health Ratio:        {}",
+        portfolio
+            .health_ratio
+            .map_or_else(|| "n/a".to_owned(), |h| format!("{:.4}", h))
+    );
+
+    Ok(())
+}
+
+/// The function handler to refresh the prices of the margin pool positions and the
+/// metadata of all positions in a margin account, approved once and sent in batches.
+fn process_refresh(cfg: &Config, margin_account: &Pubkey) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    assert_exists!(&program, MarginAccount, margin_account);
+
+    let sp = Spinner::new("Building position refresh instructions");
+    let acc = program.account::<MarginAccount>(*margin_account)?;
+
+    let mut ixs: Vec<(Instruction, &str)> =
+        refresh_position_instructions(cfg, &acc, margin_account)?
+            .into_iter()
+            .map(|ix| (ix, "jet_margin_pool::MarginRefreshPosition"))
+            .collect();
+
+    ixs.extend(
+        refresh_metadata_instructions(cfg, &acc, margin_account)?
+            .into_iter()
+            .map(|ix| (ix, "jet_margin::RefreshPositionMetadata")),
+    );
+
+    sp.finish_with_message("Instructions compiled");
+
+    if ixs.is_empty() {
+        println!(
+            "No positions to refresh in margin account {}",
+            margin_account
+        );
+        return Ok(());
+    }
+
+    let batches = ixs
+        .chunks(REFRESH_BATCH_SIZE)
+        .map(|batch| {
+            let mut req = program.request();
+            let mut ix_names = Vec::<&str>::new();
+
+            for (ix, name) in batch {
+                req = req.instruction(ix.clone());
+                ix_names.push(*name);
+            }
+
+            (req.signer(signer.as_ref()), ix_names)
+        })
+        .collect();
+
+    send_batches_with_approval(cfg, batches)
+}
+
 /// The function handler to allow users to register a new margin position on one of
 /// their accounts for an argued token mint throug the `jet_margin::RegisterPosition` instruction.
 fn process_register(cfg: &Config, margin_account: &Pubkey, position_mint: &Pubkey) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
-    let (metadata_program, _) = create_program_client(&cfg.clone_with_program(jet_metadata::ID)); // TODO: make configurable override (?)
+    let (program, signer) = create_program_client(cfg)?;
+
+    let token_account = find_position_token_account(cfg, margin_account, position_mint);
+    assert_not_exists!(&program, TokenAccount, &token_account);
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .instruction(register_position_instruction(
+                cfg,
+                &signer.pubkey(),
+                margin_account,
+                position_mint,
+            )?)
+            .signer(signer.as_ref()),
+        vec!["jet_margin::RegisterPosition"],
+    )?;
+
+    Ok(())
+}
+
+/// The function handler to value the positions of one or all margin accounts and display
+/// those with a collateral shortfall or an ongoing liquidation ranked by their shortfall.
+fn process_unhealthy(
+    cfg: &Config,
+    address: &Option<Pubkey>,
+    scan: bool,
+    display: Option<DisplayOptions>,
+) -> Result<()> {
+    let program = create_read_only_client(cfg);
+    let metadata_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::Metadata)));
+
+    let sp = Spinner::new("Valuing margin accounts");
+
+    let margins: Vec<(Pubkey, MarginAccount)> = match address {
+        Some(addr) => vec![(*addr, program.account::<MarginAccount>(*addr)?)],
+        None if scan => program.accounts(vec![RpcFilterType::DataSize(
+            8 + std::mem::size_of::<MarginAccount>() as u64,
+        )])?,
+        None => return Err(anyhow!("either a margin account or `--scan` is required")),
+    };
+
+    // Position metadata and mint decimals are shared across
+    // accounts so they are only fetched once per position token
+    let mut cache = HashMap::new();
+    let mut candidates = Vec::new();
+
+    for (addr, acc) in &margins {
+        match value_positions(cfg, &program, &metadata_program, acc, &mut cache) {
+            Ok(positions) => candidates.push(LiquidationCandidate::new(
+                &acc.owner,
+                acc.verify_not_liquidating().is_err(),
+                &Portfolio::new(addr, positions),
+            )),
+            Err(err) => eprintln!("Skipping margin account {}: {}", addr, err),
+        }
+    }
+
+    sp.finish_with_message("Accounts valued");
+
+    let candidates = rank_liquidation_candidates(candidates);
+
+    if let Some(opts) = display {
+        return print_serialized(candidates, &opts);
+    }
 
-    let token_account = Pubkey::find_program_address(
-        &[margin_account.as_ref(), position_mint.as_ref()],
-        &token_program,
+    if candidates.is_empty() {
+        println!("No margin accounts are candidates for liquidation");
+        return Ok(());
+    }
+
+    print_table(
+        &[
+            "Margin Account",
+            "Owner",
+            "Equity",
+            "Weighted Collateral",
+            "Required Collateral",
+            "Shortfall",
+            "Liquidating",
+        ],
+        &candidates
+            .iter()
+            .map(|c| {
+                vec![
+                    c.account.clone(),
+                    c.owner.clone(),
+                    format!("{:.2}", c.equity),
+                    format!("{:.2}", c.weighted_collateral),
+                    format!("{:.2}", c.required_collateral),
+                    format!("{:.2}", c.shortfall),
+                    c.liquidating.to_string(),
+                ]
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(())
+}
+
+/// Build the `jet_margin::AccountingInvoke` instruction that wraps an adapter program
+/// instruction invoked on behalf of a margin account without its signature.
+pub(crate) fn accounting_invoke_instruction(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    adapter_ix: Instruction,
+) -> Instruction {
+    let mut ix = build_instruction(
+        &cfg.programs.get(JetProgram::Margin),
+        accounts::AccountingInvoke {
+            margin_account: *margin_account,
+            adapter_program: adapter_ix.program_id,
+            adapter_metadata: derive_adapter_metadata(
+                &adapter_ix.program_id,
+                &cfg.programs.get(JetProgram::Metadata),
+            ),
+        },
+        instruction::AccountingInvoke {
+            account_metas: compact_account_metas(&adapter_ix.accounts),
+            data: adapter_ix.data,
+        },
+    );
+
+    ix.accounts
+        .extend(adapter_remaining_accounts(adapter_ix.accounts));
+    ix
+}
+
+/// Build the `jet_margin::AdapterInvoke` instruction that wraps an adapter program
+/// instruction invoked with the signature of a margin account by its owner.
+pub(crate) fn adapter_invoke_instruction(
+    cfg: &Config,
+    owner: &Pubkey,
+    margin_account: &Pubkey,
+    adapter_ix: Instruction,
+) -> Result<Instruction> {
+    let adapter_metadata = verify_adapter_registration(cfg, &adapter_ix.program_id)?;
+
+    let mut ix = build_instruction(
+        &cfg.programs.get(JetProgram::Margin),
+        accounts::AdapterInvoke {
+            owner: *owner,
+            margin_account: *margin_account,
+            adapter_program: adapter_ix.program_id,
+            adapter_metadata,
+        },
+        instruction::AdapterInvoke {
+            account_metas: compact_account_metas(&adapter_ix.accounts),
+            data: adapter_ix.data,
+        },
+    );
+
+    ix.accounts
+        .extend(adapter_remaining_accounts(adapter_ix.accounts));
+    Ok(ix)
+}
+
+/// Build the `jet_margin::LiquidatorInvoke` instruction that wraps an adapter program
+/// instruction invoked on behalf of a margin account by the liquidator in control of it.
+fn liquidator_invoke_instruction(
+    cfg: &Config,
+    liquidator: &Pubkey,
+    margin_account: &Pubkey,
+    adapter_ix: Instruction,
+) -> Result<Instruction> {
+    let adapter_metadata = verify_adapter_registration(cfg, &adapter_ix.program_id)?;
+
+    let mut ix = build_instruction(
+        &cfg.programs.get(JetProgram::Margin),
+        accounts::LiquidatorInvoke {
+            liquidator: *liquidator,
+            liquidation: derive_liquidation(
+                margin_account,
+                liquidator,
+                &cfg.programs.get(JetProgram::Margin),
+            ),
+            margin_account: *margin_account,
+            adapter_program: adapter_ix.program_id,
+            adapter_metadata,
+        },
+        instruction::LiquidatorInvoke {
+            account_metas: compact_account_metas(&adapter_ix.accounts),
+            data: adapter_ix.data,
+        },
+    );
+
+    ix.accounts
+        .extend(adapter_remaining_accounts(adapter_ix.accounts));
+    Ok(ix)
+}
+
+/// Verify that the adapter program is registered with the metadata program and
+/// return the public key of its `jet_metadata::MarginAdapterMetadata` account.
+fn verify_adapter_registration(cfg: &Config, adapter_program: &Pubkey) -> Result<Pubkey> {
+    let metadata_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::Metadata)));
+    let address = derive_adapter_metadata(adapter_program, &metadata_program.id());
+
+    if !account_exists(&metadata_program, &address)? {
+        return Err(anyhow!(
+            "program {} is not registered as a margin adapter",
+            adapter_program
+        ));
+    }
+
+    let metadata = metadata_program.account::<MarginAdapterMetadata>(address)?;
+    if metadata.adapter_program != *adapter_program {
+        return Err(anyhow!(
+            "adapter metadata {} is registered for program {} rather than {}",
+            address,
+            metadata.adapter_program,
+            adapter_program
+        ));
+    }
+
+    Ok(address)
+}
+
+/// Convert the account metas of an adapter instruction into
+/// the compact form expected by the margin program.
+fn compact_account_metas(metas: &[AccountMeta]) -> Vec<CompactAccountMeta> {
+    metas
+        .iter()
+        .map(|meta| CompactAccountMeta {
+            is_signer: meta.is_signer.into(),
+            is_writable: meta.is_writable.into(),
+        })
+        .collect()
+}
+
+/// Convert the account metas of an adapter instruction into the remaining accounts
+/// of the margin program instruction, where no account is a transaction signer since
+/// the margin program signs for the margin account itself during invocation.
+fn adapter_remaining_accounts(metas: Vec<AccountMeta>) -> Vec<AccountMeta> {
+    metas
+        .into_iter()
+        .map(|meta| AccountMeta {
+            is_signer: false,
+            ..meta
+        })
+        .collect()
+}
+
+/// Find the seeds of all existing margin accounts for the owner by
+/// re-deriving each seed until all of their accounts are accounted for.
+fn find_used_seeds(program: &Program, owner: &Pubkey) -> Result<Vec<u16>> {
+    let mut remaining: Vec<Pubkey> = program
+        .accounts::<MarginAccount>(owned_margin_account_filters(owner))?
+        .into_iter()
+        .map(|(pk, _)| pk)
+        .collect();
+
+    let mut used = Vec::with_capacity(remaining.len());
+    for seed in 0..=u16::MAX {
+        if remaining.is_empty() {
+            break;
+        }
+
+        let pk = derive_margin_account(owner, seed, &program.id());
+        if let Some(idx) = remaining.iter().position(|r| *r == pk) {
+            remaining.swap_remove(idx);
+            used.push(seed);
+        }
+    }
+
+    Ok(used)
+}
+
+/// Find the `AnchorError` message logged by the failing program of a transaction.
+fn anchor_error_log(logs: &[String]) -> Option<String> {
+    logs.iter()
+        .rev()
+        .filter_map(|log| log.strip_prefix("Program log: "))
+        .find(|msg| msg.starts_with("AnchorError"))
+        .map(ToOwned::to_owned)
+}
+
+/// Find the positions in a margin account with invalid prices or prices
+/// that have not been updated within the position's maximum staleness.
+fn find_stale_positions(account: &MarginAccount) -> Result<Vec<&AccountPosition>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(account
+        .positions()
+        .filter(|pos| {
+            pos.price.is_valid == 0
+                || now.saturating_sub(pos.price.timestamp) > pos.collateral_max_staleness
+        })
+        .collect())
+}
+
+/// Calculate the price of a position from the price information cached in the margin account.
+fn position_price(position: &AccountPosition) -> f64 {
+    position.price.value as f64 * 10f64.powi(position.price.exponent)
+}
+
+/// Filter the liquidation candidates to those with a collateral shortfall or
+/// an ongoing liquidation and sort them by descending shortfall.
+fn rank_liquidation_candidates(
+    mut candidates: Vec<LiquidationCandidate>,
+) -> Vec<LiquidationCandidate> {
+    candidates.retain(|c| c.shortfall > 0.0 || c.liquidating);
+    candidates.sort_by(|a, b| {
+        b.shortfall
+            .partial_cmp(&a.shortfall)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    candidates
+}
+
+/// Find the lowest seed that is not in the list of used seeds.
+fn lowest_unused_seed(used: &[u16]) -> Option<u16> {
+    let used: HashSet<&u16> = used.iter().collect();
+    (0..=u16::MAX).find(|seed| !used.contains(seed))
+}
+
+/// Build the RPC filters to find all `jet_margin::MarginAccount`s owned by the argued owner.
+fn owned_margin_account_filters(owner: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::DataSize(8 + std::mem::size_of::<MarginAccount>() as u64),
+        RpcFilterType::Memcmp(Memcmp {
+            offset: 16,
+            bytes: MemcmpEncodedBytes::Bytes(owner.to_bytes().to_vec()),
+            encoding: None,
+        }),
+    ]
+}
+
+/// Build the `jet_margin::RefreshPositionMetadata` instructions to update each position in a
+/// margin account with the current values of its `jet_metadata::PositionTokenMetadata`.
+fn refresh_metadata_instructions(
+    cfg: &Config,
+    account: &MarginAccount,
+    margin_account: &Pubkey,
+) -> Result<Vec<Instruction>> {
+    account
+        .positions()
+        .map(|pos| {
+            Ok(build_instruction(
+                &cfg.program_id,
+                accounts::RefreshPositionMetadata {
+                    margin_account: *margin_account,
+                    metadata: find_position_metadata(cfg, &pos.token)?,
+                },
+                instruction::RefreshPositionMetadata {},
+            ))
+        })
+        .collect()
+}
+
+/// Build the instructions to refresh the prices of each position in a margin account
+/// that is managed by the margin pool program through its `MarginRefreshPosition`.
+pub(crate) fn refresh_position_instructions(
+    cfg: &Config,
+    account: &MarginAccount,
+    margin_account: &Pubkey,
+) -> Result<Vec<Instruction>> {
+    let pool_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::MarginPool)));
+    let metadata_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::Metadata)));
+
+    account
+        .positions()
+        .filter(|pos| pos.adapter == pool_program.id())
+        .map(|pos| {
+            let metadata = metadata_program
+                .account::<PositionTokenMetadata>(find_position_metadata(cfg, &pos.token)?)?;
+            let margin_pool =
+                derive_margin_pool(&metadata.underlying_token_mint, &pool_program.id());
+            let pool = pool_program.account::<MarginPool>(margin_pool)?;
+
+            Ok(accounting_invoke_instruction(
+                cfg,
+                margin_account,
+                build_instruction(
+                    &pool_program.id(),
+                    jet_margin_pool::accounts::MarginRefreshPosition {
+                        margin_account: *margin_account,
+                        margin_pool,
+                        token_price_oracle: pool.token_price_oracle,
+                    },
+                    jet_margin_pool::instruction::MarginRefreshPosition {},
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Value each registered position in a margin account from its token account balance, cached
+/// price and the `jet_metadata::PositionTokenMetadata` collateral weight, memoizing the
+/// metadata and mint decimals of each position token mint in the argued cache.
+fn value_positions(
+    cfg: &Config,
+    program: &Program,
+    metadata_program: &Program,
+    account: &MarginAccount,
+    cache: &mut HashMap<Pubkey, (PositionTokenMetadata, u8)>,
+) -> Result<Vec<PortfolioPosition>> {
+    account
+        .positions()
+        .map(|pos| {
+            if !cache.contains_key(&pos.token) {
+                let metadata = metadata_program
+                    .account::<PositionTokenMetadata>(find_position_metadata(cfg, &pos.token)?)?;
+                let decimals = mint_decimals(program, &pos.token)?;
+                cache.insert(pos.token, (metadata, decimals));
+            }
+
+            let (metadata, decimals) = &cache[&pos.token];
+            let amount = program.account::<TokenAccount>(pos.address)?.amount;
+
+            let mut position = PortfolioPosition::new(
+                &pos.token,
+                &metadata.token_kind,
+                amount as f64 / 10f64.powi(*decimals as i32),
+                position_price(pos),
+                metadata.collateral_weight,
+            );
+            position.token_symbol = cfg.tokens.symbol_or_mint(&pos.token);
+            Ok(position)
+        })
+        .collect()
+}
+
+/// Find the public key of the token account that holds the balance
+/// of a registered position in a margin account.
+pub(crate) fn find_position_token_account(
+    cfg: &Config,
+    margin_account: &Pubkey,
+    position_mint: &Pubkey,
+) -> Pubkey {
+    derive_position_token_account(
+        margin_account,
+        position_mint,
+        &cfg.programs.get(JetProgram::Margin),
     )
-    .0;
+}
 
-    assert_not_exists!(&program, TokenAccount, &token_account);
+/// Find the public key of the `jet_metadata::PositionTokenMetadata` account
+/// for the argued position token mint.
+pub(crate) fn find_position_metadata(cfg: &Config, position_mint: &Pubkey) -> Result<Pubkey> {
+    let metadata_program =
+        create_read_only_client(&cfg.clone_with_program(cfg.programs.get(JetProgram::Metadata)));
 
     let meta_accounts: Vec<Pubkey> = metadata_program
         .accounts::<PositionTokenMetadata>(vec![
@@ -330,47 +1518,180 @@ fn process_register(cfg: &Config, margin_account: &Pubkey, position_mint: &Pubke
         .map(|acc| acc.0)
         .collect();
 
-    if meta_accounts.is_empty() {
-        return Err(anyhow!(
+    meta_accounts.first().copied().ok_or_else(|| {
+        anyhow!(
             "no `jet_metadata::PositionTokenMetadata` account for token mint {} was found",
             position_mint,
-        ));
-    }
+        )
+    })
+}
 
-    send_with_approval(
-        cfg,
-        program
-            .request()
-            .accounts(accounts::RegisterPosition {
-                authority: signer.pubkey(),
-                payer: signer.pubkey(),
-                margin_account: *margin_account,
-                position_token_mint: *position_mint,
-                metadata: meta_accounts[0],
-                token_account,
-                token_program,
-                system_program,
-                rent,
-            })
-            .args(instruction::RegisterPosition {})
-            .signer(signer.as_ref()),
-        vec!["jet_margin::RegisterPosition"],
-    )?;
+/// Build the `jet_margin::RegisterPosition` instruction to register a new
+/// position for the argued token mint in a margin account.
+pub(crate) fn register_position_instruction(
+    cfg: &Config,
+    authority: &Pubkey,
+    margin_account: &Pubkey,
+    position_mint: &Pubkey,
+) -> Result<Instruction> {
+    let metadata = find_position_metadata(cfg, position_mint)?;
 
-    Ok(())
+    Ok(build_instruction(
+        &cfg.programs.get(JetProgram::Margin),
+        accounts::RegisterPosition {
+            authority: *authority,
+            payer: cfg.fee_payer.pubkey(),
+            margin_account: *margin_account,
+            position_token_mint: *position_mint,
+            metadata,
+            token_account: find_position_token_account(cfg, margin_account, position_mint),
+            token_program,
+            system_program,
+            rent,
+        },
+        instruction::RegisterPosition {},
+    ))
 }
 
 #[cfg(test)]
 mod tests {
+    use clap::Parser;
     use serde_test::{assert_ser_tokens, Token};
 
     use super::*;
 
+    #[test]
+    fn select_positional_margin_account() {
+        let pk = Pubkey::new_unique();
+        let opts =
+            crate::Opts::try_parse_from(["jet", "margin", "check", &pk.to_string()]).unwrap();
+
+        match opts.command {
+            crate::Command::Margin {
+                subcmd: MarginCommand::Check { account, .. },
+                ..
+            } => assert_eq!(account.find(&Config::default()).unwrap(), Some(pk)),
+            _ => panic!("expected the margin check command"),
+        }
+
+        assert!(crate::Opts::try_parse_from([
+            "jet",
+            "margin",
+            "check",
+            &pk.to_string(),
+            "--account",
+            &pk.to_string()
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn lowest_unused_seed_fills_gaps() {
+        assert_eq!(lowest_unused_seed(&[]), Some(0));
+        assert_eq!(lowest_unused_seed(&[0, 1, 3]), Some(2));
+        assert_eq!(lowest_unused_seed(&[1, 0, 2]), Some(3));
+
+        let all: Vec<u16> = (0..=u16::MAX).collect();
+        assert_eq!(lowest_unused_seed(&all), None);
+    }
+
+    #[test]
+    fn portfolio_aggregates_positions() {
+        let portfolio = Portfolio::new(
+            &Pubkey::default(),
+            vec![
+                PortfolioPosition::new(
+                    &Pubkey::default(),
+                    &TokenKind::Collateral,
+                    10.0,
+                    2.0,
+                    7_500,
+                ),
+                PortfolioPosition::new(&Pubkey::default(), &TokenKind::Claim, 5.0, 1.0, 12_500),
+                PortfolioPosition::new(&Pubkey::default(), &TokenKind::NonCollateral, 3.0, 1.0, 0),
+            ],
+        );
+
+        assert_eq!(portfolio.equity, 18.0);
+        assert_eq!(portfolio.liabilities, 5.0);
+        assert_eq!(portfolio.weighted_collateral, 15.0);
+        assert_eq!(portfolio.required_collateral, 6.25);
+        assert_eq!(portfolio.health_ratio, Some(2.4));
+    }
+
+    #[test]
+    fn portfolio_without_liabilities_has_no_ratio() {
+        let portfolio = Portfolio::new(
+            &Pubkey::default(),
+            vec![PortfolioPosition::new(
+                &Pubkey::default(),
+                &TokenKind::Collateral,
+                1.0,
+                1.0,
+                10_000,
+            )],
+        );
+
+        assert_eq!(portfolio.health_ratio, None);
+        assert_eq!(portfolio.equity, 1.0);
+    }
+
+    #[test]
+    fn liquidation_candidates_ranked_by_shortfall() {
+        let portfolio = |weight| {
+            Portfolio::new(
+                &Pubkey::default(),
+                vec![
+                    PortfolioPosition::new(
+                        &Pubkey::default(),
+                        &TokenKind::Collateral,
+                        10.0,
+                        1.0,
+                        weight,
+                    ),
+                    PortfolioPosition::new(&Pubkey::default(), &TokenKind::Claim, 8.0, 1.0, 10_000),
+                ],
+            )
+        };
+
+        let healthy = LiquidationCandidate::new(&Pubkey::default(), false, &portfolio(10_000));
+        let minor = LiquidationCandidate::new(&Pubkey::default(), false, &portfolio(5_000));
+        let major = LiquidationCandidate::new(&Pubkey::default(), false, &portfolio(2_500));
+        let liquidating = LiquidationCandidate::new(&Pubkey::default(), true, &portfolio(10_000));
+
+        assert_eq!(healthy.shortfall, 0.0);
+        assert_eq!(minor.shortfall, 3.0);
+        assert_eq!(major.shortfall, 5.5);
+
+        let ranked = rank_liquidation_candidates(vec![healthy, minor, liquidating, major]);
+        let shortfalls: Vec<f64> = ranked.iter().map(|c| c.shortfall).collect();
+        assert_eq!(shortfalls, vec![5.5, 3.0, 0.0]);
+        assert!(ranked[2].liquidating);
+    }
+
+    #[test]
+    fn anchor_error_from_logs() {
+        let logs = vec![
+            "Program JPMRGNgRk3w2pzBM1RLNBnpGxQYsFQ3yXKpuz7sbhrR invoke [1]".to_owned(),
+            "Program log: Instruction: VerifyHealthy".to_owned(),
+            "Program log: AnchorError thrown in src/lib.rs:42. Error Code: Unhealthy. Error Number: 141001. Error Message: The account is not healthy.".to_owned(),
+            "Program JPMRGNgRk3w2pzBM1RLNBnpGxQYsFQ3yXKpuz7sbhrR failed: custom program error: 0x226c9".to_owned(),
+        ];
+
+        assert_eq!(
+            anchor_error_log(&logs).unwrap(),
+            "AnchorError thrown in src/lib.rs:42. Error Code: Unhealthy. Error Number: 141001. Error Message: The account is not healthy."
+        );
+        assert_eq!(anchor_error_log(&logs[..2]), None);
+    }
+
     #[test]
     fn account_health_serialization() {
         let ah = AccountHealth {
             healthy: true,
             liquidating: false,
+            stale_positions: vec![],
+            failure: None,
         };
 
         assert_ser_tokens(
@@ -378,12 +1699,47 @@ mod tests {
             &[
                 Token::Struct {
                     name: "AccountHealth",
-                    len: 2,
+                    len: 3,
                 },
                 Token::Str("healthy"),
                 Token::Bool(true),
                 Token::Str("liquidating"),
                 Token::Bool(false),
+                Token::Str("stale_positions"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn account_health_serialization_with_failure() {
+        let ah = AccountHealth {
+            healthy: false,
+            liquidating: false,
+            stale_positions: vec![Pubkey::default().to_string()],
+            failure: Some("unhealthy".into()),
+        };
+
+        assert_ser_tokens(
+            &ah,
+            &[
+                Token::Struct {
+                    name: "AccountHealth",
+                    len: 4,
+                },
+                Token::Str("healthy"),
+                Token::Bool(false),
+                Token::Str("liquidating"),
+                Token::Bool(false),
+                Token::Str("stale_positions"),
+                Token::Seq { len: Some(1) },
+                Token::Str("11111111111111111111111111111111"),
+                Token::SeqEnd,
+                Token::Str("failure"),
+                Token::Some,
+                Token::Str("unhealthy"),
                 Token::StructEnd,
             ],
         );
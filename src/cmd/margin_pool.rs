@@ -14,69 +14,252 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use anchor_client::solana_client::rpc_filter::RpcFilterType;
+use anchor_client::solana_sdk::instruction::Instruction;
 use anchor_client::solana_sdk::pubkey::Pubkey;
-use anchor_spl::token::ID as token_program;
-use anyhow::Result;
-use clap::Subcommand;
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_client::solana_sdk::system_program::ID as system_program;
+use anchor_client::solana_sdk::sysvar::rent::ID as rent;
+use anchor_client::Program;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{TokenAccount, ID as token_program};
+use anyhow::{anyhow, Result};
+use clap::{Subcommand, ValueHint};
 use jet_margin::MarginAccount;
-use jet_margin_pool::{accounts, instruction, MarginPool};
+use jet_margin_pool::{accounts, instruction, Amount, MarginPool, MarginPoolConfig};
+use serde::{Deserialize, Serialize};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use crate::config::{Config, Overrides};
-use crate::macros::assert_exists;
-use crate::program::{create_program_client, send_with_approval};
-use crate::pubkey::derive_margin_pool;
-use crate::terminal::{print_serialized, DisplayOptions};
+use super::margin::{
+    adapter_invoke_instruction, find_position_token_account, register_position_instruction,
+    MarginAccountSelector,
+};
+
+use crate::amount::{format_units, mint_decimals, TokenAmount, UiAmount};
+use crate::config::{parse_address, Config, JetProgram, Overrides};
+use crate::macros::{assert_exists, assert_not_exists};
+use crate::program::{
+    account_exists, build_instruction, create_program_client, create_read_only_client,
+    send_with_approval,
+};
+use crate::pubkey::{derive_margin_pool, derive_margin_pool_addresses, MarginPoolAddresses};
+use crate::terminal::{print_serialized, print_table, DisplayOptions};
+use crate::token::{TokenMint, TokenRegistry};
+
+/// Utility struct for deserializing a margin pool configuration
+/// from a TOML or JSON file provided by a pool administrator.
+#[derive(Debug, Deserialize, PartialEq)]
+struct PoolConfigFile {
+    flags: u64,
+    utilization_rate_1: u16,
+    utilization_rate_2: u16,
+    borrow_rate_0: u16,
+    borrow_rate_1: u16,
+    borrow_rate_2: u16,
+    borrow_rate_3: u16,
+    management_fee_rate: u16,
+    management_fee_collect_threshold: u64,
+    oracle: PoolOracleFile,
+}
+
+/// The Pyth oracle accounts section of a margin pool configuration file.
+#[derive(Debug, Deserialize, PartialEq)]
+struct PoolOracleFile {
+    product: String,
+    price: String,
+}
+
+impl PoolConfigFile {
+    /// Read and deserialize the configuration file at the argued path
+    /// as JSON if it has a `.json` extension, otherwise as TOML.
+    fn load(path: &Path) -> Result<Self> {
+        let data = read_to_string(path)?;
+        if path.extension().map_or(false, |ext| ext == "json") {
+            Ok(serde_json::from_str(&data)?)
+        } else {
+            Ok(toml::from_str(&data)?)
+        }
+    }
+
+    /// Convert the file contents into the `jet_margin_pool::MarginPoolConfig` instruction argument.
+    fn to_pool_config(&self) -> MarginPoolConfig {
+        MarginPoolConfig {
+            flags: self.flags,
+            utilization_rate_1: self.utilization_rate_1,
+            utilization_rate_2: self.utilization_rate_2,
+            borrow_rate_0: self.borrow_rate_0,
+            borrow_rate_1: self.borrow_rate_1,
+            borrow_rate_2: self.borrow_rate_2,
+            borrow_rate_3: self.borrow_rate_3,
+            management_fee_rate: self.management_fee_rate,
+            management_fee_collect_threshold: self.management_fee_collect_threshold,
+        }
+    }
+
+    /// Parse the public keys of the Pyth product and price oracle accounts.
+    fn oracle_keys(&self) -> Result<(Pubkey, Pubkey)> {
+        Ok((
+            Pubkey::from_str(&self.oracle.product)
+                .map_err(|_| anyhow!("invalid `oracle.product` public key"))?,
+            Pubkey::from_str(&self.oracle.price)
+                .map_err(|_| anyhow!("invalid `oracle.price` public key"))?,
+        ))
+    }
+}
+
+/// Utility struct for serialization of the computed interest
+/// rates and liquidity of a margin pool for display purposes.
+#[derive(Debug, Serialize)]
+struct PoolRates {
+    address: String,
+    token_mint: String,
+    #[serde(skip)]
+    token_symbol: String,
+    utilization: f64,
+    borrow_apr: f64,
+    borrow_apy: f64,
+    deposit_apr: f64,
+    deposit_apy: f64,
+    deposit_note_exchange_rate: f64,
+    loan_note_exchange_rate: f64,
+    available_liquidity: String,
+}
+
+impl PoolRates {
+    /// Compute the rates of a margin pool from its current state and rate curve configuration.
+    fn new(
+        address: &Pubkey,
+        pool: &MarginPool,
+        decimals: u8,
+        tokens: &TokenRegistry,
+    ) -> Result<Self> {
+        let borrowed = number_to_f64(&pool.borrowed_tokens);
+        let fees = number_to_f64(&pool.uncollected_fees);
+        let deposited = pool.deposit_tokens as f64;
+        let total = deposited + borrowed;
+
+        let utilization = if total > 0.0 { borrowed / total } else { 0.0 };
+        let borrow_apr = borrow_rate(utilization, &pool.config);
+        let deposit_apr = borrow_apr
+            * utilization
+            * (1.0 - f64::from(pool.config.management_fee_rate) / BPS_PER_UNIT);
+
+        Ok(Self {
+            address: address.to_string(),
+            token_mint: pool.token_mint.to_string(),
+            token_symbol: tokens.symbol_or_mint(&pool.token_mint),
+            utilization,
+            borrow_apr,
+            borrow_apy: apr_to_apy(borrow_apr),
+            deposit_apr,
+            deposit_apy: apr_to_apy(deposit_apr),
+            deposit_note_exchange_rate: exchange_rate(total - fees, pool.deposit_notes),
+            loan_note_exchange_rate: exchange_rate(borrowed, pool.loan_notes),
+            available_liquidity: format_units(pool.deposit_tokens, decimals)?,
+        })
+    }
+}
 
 /// Margin pool program based subcommand enum variants.
 #[derive(Debug, Subcommand)]
 pub enum MarginPoolCommand {
     /// Borrow funds from a margin pool.
     Borrow {
-        /// The token amount to borrow from the pool.
+        /// The token amount to borrow from the pool (e.g. `1.5` or `1.5 USDC`).
         #[clap(value_parser)]
-        amount: u64,
-        /// Margin account to sign the borrow.
-        #[clap(long, value_parser)]
-        account: Pubkey,
-        /// Account to receive borrowed tokens.
+        amount: TokenAmount,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Interpret the amount as a raw value in the mint's base units.
         #[clap(long, value_parser)]
-        deposit_account: Pubkey,
-        /// Account to receive the loan notes.
-        #[clap(long, value_parser)]
-        loan_account: Pubkey,
+        base_units: bool,
+        /// Account to receive borrowed tokens (defaults to the margin account's deposit note position).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        deposit_account: Option<Pubkey>,
+        /// Account to receive the loan notes (defaults to the margin account's loan note position).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        loan_account: Option<Pubkey>,
         /// Target margin pool.
-        #[clap(long, value_parser)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", required_unless_present = "token_mint")]
+        pool: Option<Pubkey>,
+        /// Token mint to derive margin pool.
+        #[clap(long, value_parser, value_name = "MINT", conflicts_with = "pool")]
+        token_mint: Option<TokenMint>,
+    },
+    /// Update the configuration of a margin pool as its authority.
+    Configure {
+        /// Path to the TOML or JSON pool configuration file.
+        #[clap(value_parser, value_hint = ValueHint::FilePath)]
+        config: PathBuf,
+        /// Target margin pool.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", required_unless_present = "token_mint")]
+        pool: Option<Pubkey>,
+        /// Token mint to derive margin pool.
+        #[clap(long, value_parser, value_name = "MINT", conflicts_with = "pool")]
+        token_mint: Option<TokenMint>,
+    },
+    /// Create a new margin pool for a token as its authority.
+    Create {
+        /// The underlying token mint address.
+        #[clap(long, value_parser, value_name = "MINT")]
+        token_mint: TokenMint,
     },
     /// Deposit into an existing margin pool.
     Deposit {
-        /// The token amount to deposit into the pool.
+        /// The token amount to deposit into the pool (e.g. `1.5`, `1.5 USDC` or `max`).
         #[clap(value_parser)]
-        amount: u64,
-        /// Margin account that owns the source token account.
-        #[clap(long, value_parser)]
-        account: Pubkey,
-        /// Destination token account address.
+        amount: TokenAmount,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Interpret the amount as a raw value in the mint's base units.
         #[clap(long, value_parser)]
-        destination: Pubkey,
+        base_units: bool,
+        /// Destination token account address (defaults to the margin account's deposit note position).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        destination: Option<Pubkey>,
         /// Target margin pool.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", required_unless_present = "token_mint")]
+        pool: Option<Pubkey>,
+        /// Fund source token account (defaults to the depositor's associated token account).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        source: Option<Pubkey>,
+        /// Token mint to derive margin pool.
+        #[clap(long, value_parser, value_name = "MINT", conflicts_with = "pool")]
+        token_mint: Option<TokenMint>,
+        /// Deposit the funds of the selected margin account through the margin program.
         #[clap(long, value_parser)]
-        pool: Pubkey,
-        /// Fund source token account.
-        #[clap(long, value_parser)]
-        source: Pubkey,
+        via_margin: bool,
     },
     /// Derive the public key of a margin pool.
     Derive {
         /// The underlying token mint address.
-        #[clap(long, value_parser)]
-        token_mint: Pubkey,
+        #[clap(long, value_parser, value_name = "MINT")]
+        token_mint: TokenMint,
     },
     /// Get the account data for a margin pool or all that exist.
     Get {
         /// Public key of specific pool to get.
-        #[clap(value_parser)]
+        #[clap(value_parser = parse_address)]
+        address: Option<Pubkey>,
+        /// Output data as serialized JSON.
+        #[clap(long, value_parser)]
+        json: bool,
+        /// Formatted data output.
+        #[clap(long, value_parser)]
+        pretty: bool,
+        /// Token mint to derive margin pool.
+        #[clap(long, value_parser, value_name = "MINT", conflicts_with = "address")]
+        token_mint: Option<TokenMint>,
+    },
+    /// Display the interest rates, utilization and liquidity of margin pools.
+    Rates {
+        /// Public key of specific pool to analyze.
+        #[clap(value_parser = parse_address, required_unless_present_any = &["all", "token_mint"])]
         address: Option<Pubkey>,
+        /// Rank all existing margin pools by deposit APY.
+        #[clap(long, value_parser, conflicts_with_all = &["address", "token_mint"])]
+        all: bool,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
         json: bool,
@@ -84,31 +267,116 @@ pub enum MarginPoolCommand {
         #[clap(long, value_parser)]
         pretty: bool,
         /// Token mint to derive margin pool.
-        #[clap(long, value_parser, conflicts_with = "address")]
-        token_mint: Option<Pubkey>,
+        #[clap(long, value_parser, value_name = "MINT", conflicts_with = "address")]
+        token_mint: Option<TokenMint>,
+    },
+    /// Repay a loan from a margin pool.
+    Repay {
+        /// The maximum token amount to repay to the pool (e.g. `1.5`, `1.5 USDC` or `max`).
+        #[clap(value_parser, required_unless_present = "all")]
+        amount: Option<TokenAmount>,
+        /// Repay the full outstanding loan balance.
+        #[clap(long, value_parser, conflicts_with = "amount")]
+        all: bool,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Interpret the amount as a raw value in the mint's base units.
+        #[clap(long, value_parser)]
+        base_units: bool,
+        /// Account holding the deposit notes used to repay.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        deposit_account: Pubkey,
+        /// Account holding the loan notes to be repaid.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        loan_account: Pubkey,
+        /// Target margin pool.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Pubkey,
+    },
+    /// Withdraw deposited funds from a margin pool.
+    Withdraw {
+        /// The token amount to withdraw from the pool (e.g. `1.5`, `1.5 USDC` or `max`).
+        #[clap(value_parser, required_unless_present = "all")]
+        amount: Option<TokenAmount>,
+        /// Withdraw the full deposit note balance.
+        #[clap(long, value_parser, conflicts_with = "amount")]
+        all: bool,
+        #[clap(flatten)]
+        account: MarginAccountSelector,
+        /// Interpret the amount as a raw value in the mint's base units.
+        #[clap(long, value_parser)]
+        base_units: bool,
+        /// Token account to receive the withdrawn funds (defaults to your associated token account).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        destination: Option<Pubkey>,
+        /// Target margin pool.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Pubkey,
+        /// Deposit note account to withdraw from (defaults to the margin account's deposit note position).
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", required_unless_present = "via_margin")]
+        source: Option<Pubkey>,
+        /// Withdraw the deposits of the selected margin account through the margin program.
+        #[clap(long, value_parser)]
+        via_margin: bool,
     },
 }
 
 /// The main entry point and handler for all margin pool
 /// program interaction commands.
-pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &MarginPoolCommand) -> Result<()> {
-    let cfg = Config::new(overrides, *program_id)?;
+pub fn entry(
+    overrides: &Overrides,
+    program_id: &Option<Pubkey>,
+    subcmd: &MarginPoolCommand,
+) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::MarginPool, *program_id)?;
     match subcmd {
         MarginPoolCommand::Borrow {
             amount,
             account,
+            base_units,
             deposit_account,
             loan_account,
             pool,
-        } => process_borrow(&cfg, *amount, account, deposit_account, loan_account, pool),
+            token_mint,
+        } => process_borrow(
+            &cfg,
+            amount,
+            *base_units,
+            &account.resolve(&cfg)?,
+            deposit_account,
+            loan_account,
+            &resolve_margin_pool(&cfg, pool, token_mint)?,
+        ),
+        MarginPoolCommand::Configure {
+            config,
+            pool,
+            token_mint,
+        } => process_configure(&cfg, config, &resolve_margin_pool(&cfg, pool, token_mint)?),
+        MarginPoolCommand::Create { token_mint } => {
+            process_create(&cfg, &cfg.tokens.resolve(token_mint)?)
+        }
         MarginPoolCommand::Deposit {
             amount,
             account,
+            base_units,
             destination,
             pool,
             source,
-        } => process_deposit(&cfg, *amount, account, destination, pool, source),
-        MarginPoolCommand::Derive { token_mint } => process_derive(&cfg, token_mint),
+            token_mint,
+            via_margin,
+        } => process_deposit(
+            &cfg,
+            amount,
+            *base_units,
+            &account.find(&cfg)?,
+            destination,
+            &resolve_margin_pool(&cfg, pool, token_mint)?,
+            source,
+            &resolve_via_margin(&cfg, account, *via_margin)?,
+        ),
+        MarginPoolCommand::Derive { token_mint } => {
+            process_derive(&cfg, &cfg.tokens.resolve(token_mint)?)
+        }
         MarginPoolCommand::Get {
             address,
             json,
@@ -116,9 +384,55 @@ pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &MarginPoolComm
             token_mint,
         } => process_get(
             &cfg,
+            &find_margin_pool(&cfg, address, token_mint)?,
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
+        MarginPoolCommand::Rates {
             address,
+            all,
+            json,
+            pretty,
             token_mint,
-            DisplayOptions::from_args(*json, *pretty),
+        } => process_rates(
+            &cfg,
+            &find_margin_pool(&cfg, address, token_mint)?,
+            *all,
+            json.then(|| DisplayOptions::from_args(*json, *pretty)),
+        ),
+        MarginPoolCommand::Repay {
+            amount,
+            all: _,
+            account,
+            base_units,
+            deposit_account,
+            loan_account,
+            pool,
+        } => process_repay(
+            &cfg,
+            &amount.clone().unwrap_or(TokenAmount::Max),
+            *base_units,
+            &account.resolve(&cfg)?,
+            deposit_account,
+            loan_account,
+            pool,
+        ),
+        MarginPoolCommand::Withdraw {
+            amount,
+            all: _,
+            account,
+            base_units,
+            destination,
+            pool,
+            source,
+            via_margin,
+        } => process_withdraw(
+            &cfg,
+            &amount.clone().unwrap_or(TokenAmount::Max),
+            *base_units,
+            destination,
+            pool,
+            source,
+            &resolve_via_margin(&cfg, account, *via_margin)?,
         ),
     }
 }
@@ -126,69 +440,278 @@ pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &MarginPoolComm
 /// The function handler to allow users to borrow tokens from a margin pool.
 fn process_borrow(
     cfg: &Config,
-    amount: u64,
+    amount: &TokenAmount,
+    base_units: bool,
     margin_account: &Pubkey,
-    deposit_account: &Pubkey,
-    loan_account: &Pubkey,
+    deposit_account: &Option<Pubkey>,
+    loan_account: &Option<Pubkey>,
     margin_pool: &Pubkey,
 ) -> Result<()> {
-    let (program, _) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
+    let mut req = program.request();
+    let mut ix_names = Vec::<&str>::new();
 
     assert_exists!(&program, MarginAccount, margin_account);
     assert_exists!(&program, MarginPool, margin_pool);
 
     let pool = program.account::<MarginPool>(*margin_pool)?;
 
+    cfg.tokens.verify_symbol(amount, &pool.token_mint)?;
+    let decimals = mint_decimals(&program, &pool.token_mint)?;
+    let amount = amount
+        .to_base_units(decimals, base_units)?
+        .ok_or_else(|| anyhow!("`max` is not a supported amount for borrowing"))?;
+
+    // Find the deposit and loan note positions of the margin account
+    // and prepend the instructions to register any that are missing
+    let (deposit_account, loan_account) = match (deposit_account, loan_account) {
+        (Some(d), Some(l)) => (*d, *l),
+        _ => {
+            let (deposit_position, deposit_ix) = find_or_register_position(
+                cfg,
+                &program,
+                &signer.pubkey(),
+                margin_account,
+                &pool.deposit_note_mint,
+            )?;
+            let (loan_position, loan_ix) = find_or_register_position(
+                cfg,
+                &program,
+                &signer.pubkey(),
+                margin_account,
+                &pool.loan_note_mint,
+            )?;
+
+            for ix in [deposit_ix, loan_ix].into_iter().flatten() {
+                req = req.instruction(ix);
+                ix_names.push("jet_margin::RegisterPosition");
+            }
+
+            (
+                deposit_account.unwrap_or(deposit_position),
+                loan_account.unwrap_or(loan_position),
+            )
+        }
+    };
+
+    let borrow_ix = build_instruction(
+        &program.id(),
+        accounts::MarginBorrow {
+            margin_account: *margin_account,
+            margin_pool: *margin_pool,
+            loan_note_mint: pool.loan_note_mint,
+            deposit_note_mint: pool.deposit_note_mint,
+            loan_account,
+            deposit_account,
+            token_program,
+        },
+        instruction::MarginBorrow { amount },
+    );
+
+    let borrow_name = adapter_invoke_name(
+        format!(
+            "jet_margin_pool::MarginBorrow {}",
+            UiAmount::new(amount, decimals).with_symbol(cfg.tokens.symbol(&pool.token_mint))
+        ),
+        true,
+    );
+    ix_names.push(borrow_name.as_str());
+
+    send_with_approval(
+        cfg,
+        req.instruction(adapter_invoke_instruction(
+            cfg,
+            &signer.pubkey(),
+            margin_account,
+            borrow_ix,
+        )?)
+        .signer(signer.as_ref()),
+        ix_names,
+    )
+}
+
+/// The function handler for a pool authority to update the configuration of a margin pool
+/// through the `jet_margin_pool::Configure` instruction after reviewing the changes.
+fn process_configure(cfg: &Config, config_path: &Path, margin_pool: &Pubkey) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+    let margin_pool = *margin_pool;
+
+    assert_exists!(&program, MarginPool, &margin_pool);
+
+    // Load the proposed configuration and compare it against
+    // the one currently stored in the on-chain pool account
+    let file = PoolConfigFile::load(config_path)?;
+    let config = file.to_pool_config();
+    let (pyth_product, pyth_price) = file.oracle_keys()?;
+
+    let current = program.account::<MarginPool>(margin_pool)?;
+    let changes = diff_pool_config(&current, &config, &pyth_price);
+
+    if changes.is_empty() {
+        println!("No changes to the configuration of pool {}", margin_pool);
+        return Ok(());
+    }
+
+    println!("Configuration changes for pool {}:", margin_pool);
+    for (field, old, new) in &changes {
+        println!("  {}: {} -> {}", field, old, new);
+    }
+    println!();
+
     send_with_approval(
         cfg,
         program
             .request()
-            .accounts(accounts::MarginBorrow {
-                margin_account: *margin_account,
-                margin_pool: *margin_pool,
-                loan_note_mint: pool.loan_note_mint,
-                deposit_note_mint: pool.deposit_note_mint,
-                loan_account: *loan_account,
-                deposit_account: *deposit_account,
-                token_program,
+            .accounts(accounts::Configure {
+                authority: signer.pubkey(),
+                margin_pool,
+                pyth_product,
+                pyth_price,
+            })
+            .args(instruction::Configure {
+                config: Some(config),
             })
-            .args(instruction::MarginBorrow { amount }),
-        vec!["jet_margin_pool::MarginBorrow"],
+            .signer(signer.as_ref()),
+        vec!["jet_margin_pool::Configure"],
     )
 }
 
+/// The function handler for a pool authority to create a new margin pool
+/// for a token mint through the `jet_margin_pool::CreatePool` instruction.
+fn process_create(cfg: &Config, token_mint: &Pubkey) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    // Derive the public keys needed for a new margin pool and
+    // ensure that the pool address doesn't already exist
+    let MarginPoolAddresses {
+        pool,
+        vault,
+        deposit_note_mint,
+        loan_note_mint,
+    } = derive_margin_pool_addresses(token_mint, &program.id());
+
+    assert_not_exists!(&program, MarginPool, &pool);
+
+    // Build and send the `jet_margin_pool::CreatePool` transaction
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .accounts(accounts::CreatePool {
+                margin_pool: pool,
+                vault,
+                deposit_note_mint,
+                loan_note_mint,
+                token_mint: *token_mint,
+                authority: signer.pubkey(),
+                payer: cfg.fee_payer.pubkey(),
+                token_program,
+                system_program,
+                rent,
+            })
+            .args(instruction::CreatePool {})
+            .signer(signer.as_ref()),
+        vec!["jet_margin_pool::CreatePool"],
+    )?;
+
+    println!("Pubkey: {}", pool);
+
+    Ok(())
+}
+
 /// The function handler to allow users to deposit token funds into a margin pool.
+#[allow(clippy::too_many_arguments)]
 fn process_deposit(
     cfg: &Config,
-    amount: u64,
-    destination_account: &Pubkey,
-    margin_account: &Pubkey,
+    amount: &TokenAmount,
+    base_units: bool,
+    margin_account: &Option<Pubkey>,
+    destination_account: &Option<Pubkey>,
     margin_pool: &Pubkey,
-    source_account: &Pubkey,
+    source_account: &Option<Pubkey>,
+    via_margin: &Option<Pubkey>,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
+    let mut req = program.request();
+    let mut ix_names = Vec::<&str>::new();
 
-    assert_exists!(&program, MarginAccount, margin_account);
     assert_exists!(&program, MarginPool, margin_pool);
 
     let pool = program.account::<MarginPool>(*margin_pool)?;
 
-    send_with_approval(
+    // Depositing through a margin account uses its own funds and receives
+    // the deposit notes into its position rather than the signer's
+    let margin_account = via_margin.or(*margin_account);
+    let depositor = via_margin.unwrap_or_else(|| signer.pubkey());
+
+    let source = source_account
+        .unwrap_or_else(|| get_associated_token_address(&depositor, &pool.token_mint));
+
+    // Depositing the maximum uses the entire balance of the source token account
+    cfg.tokens.verify_symbol(amount, &pool.token_mint)?;
+    let decimals = mint_decimals(&program, &pool.token_mint)?;
+    let amount = match amount.to_base_units(decimals, base_units)? {
+        Some(amt) => amt,
+        None => program.account::<TokenAccount>(source)?.amount,
+    };
+
+    // Resolve the destination from the deposit note position of the margin account
+    // if one wasn't provided and prepend its registration if it is missing
+    let destination = match (destination_account, &margin_account) {
+        (Some(d), _) => *d,
+        (None, Some(m)) => {
+            assert_exists!(&program, MarginAccount, m);
+
+            let (position, register_ix) = find_or_register_position(
+                cfg,
+                &program,
+                &signer.pubkey(),
+                m,
+                &pool.deposit_note_mint,
+            )?;
+
+            if let Some(ix) = register_ix {
+                req = req.instruction(ix);
+                ix_names.push("jet_margin::RegisterPosition");
+            }
+
+            position
+        }
+        (None, None) => return Err(anyhow!("a margin account or `--destination` is required")),
+    };
+
+    let deposit_ix = invoke_through_margin(
         cfg,
-        program
-            .request()
-            .accounts(accounts::Deposit {
+        &signer.pubkey(),
+        via_margin,
+        build_instruction(
+            &program.id(),
+            accounts::Deposit {
                 margin_pool: *margin_pool,
                 vault: pool.vault,
                 deposit_note_mint: pool.deposit_note_mint,
-                depositor: *margin_account,
-                source: *source_account,
-                destination: *destination_account,
+                depositor,
+                source,
+                destination,
                 token_program,
-            })
-            .args(instruction::Deposit { amount })
-            .signer(signer.as_ref()),
-        vec!["jet_margin_pool::Deposit"],
+            },
+            instruction::Deposit { amount },
+        ),
+    )?;
+
+    let deposit_name = adapter_invoke_name(
+        format!(
+            "jet_margin_pool::Deposit {}",
+            UiAmount::new(amount, decimals).with_symbol(cfg.tokens.symbol(&pool.token_mint))
+        ),
+        via_margin.is_some(),
+    );
+    ix_names.push(deposit_name.as_str());
+
+    send_with_approval(
+        cfg,
+        req.instruction(deposit_ix).signer(signer.as_ref()),
+        ix_names,
     )
 }
 
@@ -200,19 +723,11 @@ fn process_derive(cfg: &Config, token_mint: &Pubkey) -> Result<()> {
 
 /// The function handler for fetching and displaying program account data for all existing
 /// or a specific margin pool account.
-fn process_get(
-    cfg: &Config,
-    address: &Option<Pubkey>,
-    token_mint: &Option<Pubkey>,
-    display: DisplayOptions,
-) -> Result<()> {
-    let (program, _) = create_program_client(cfg);
+fn process_get(cfg: &Config, address: &Option<Pubkey>, display: DisplayOptions) -> Result<()> {
+    let program = create_read_only_client(cfg);
 
     if let Some(addr) = address {
         return print_serialized(program.account::<MarginPool>(*addr)?, &display);
-    } else if let Some(tm) = token_mint {
-        let pool = derive_margin_pool(tm, &program.id());
-        return print_serialized(program.account::<MarginPool>(pool)?, &display);
     }
 
     let pools: Vec<MarginPool> = program
@@ -225,3 +740,566 @@ fn process_get(
 
     print_serialized(pools, &display)
 }
+
+/// The function handler for computing and displaying the interest rates, utilization, note
+/// exchange rates and available liquidity for a specific margin pool or all that exist.
+fn process_rates(
+    cfg: &Config,
+    address: &Option<Pubkey>,
+    all: bool,
+    display: Option<DisplayOptions>,
+) -> Result<()> {
+    let program = create_read_only_client(cfg);
+
+    let pools: Vec<(Pubkey, MarginPool)> = match address {
+        Some(addr) if !all => vec![(*addr, program.account::<MarginPool>(*addr)?)],
+        _ => program.accounts::<MarginPool>(vec![RpcFilterType::DataSize(
+            8 + std::mem::size_of::<MarginPool>() as u64,
+        )])?,
+    };
+
+    let mut rates = pools
+        .iter()
+        .map(|(addr, pool)| {
+            let decimals = mint_decimals(&program, &pool.token_mint)?;
+            PoolRates::new(addr, pool, decimals, &cfg.tokens)
+        })
+        .collect::<Result<Vec<PoolRates>>>()?;
+
+    rates.sort_by(|a, b| b.deposit_apy.total_cmp(&a.deposit_apy));
+
+    if let Some(opts) = display {
+        return print_serialized(rates, &opts);
+    }
+
+    print_table(
+        &[
+            "Pool",
+            "Token",
+            "Utilization",
+            "Borrow APR",
+            "Borrow APY",
+            "Deposit APR",
+            "Deposit APY",
+            "Deposit Note Rate",
+            "Loan Note Rate",
+            "Available Liquidity",
+        ],
+        &rates
+            .iter()
+            .map(|r| {
+                vec![
+                    r.address.clone(),
+                    r.token_symbol.clone(),
+                    format_percent(r.utilization),
+                    format_percent(r.borrow_apr),
+                    format_percent(r.borrow_apy),
+                    format_percent(r.deposit_apr),
+                    format_percent(r.deposit_apy),
+                    format!("{:.6}", r.deposit_note_exchange_rate),
+                    format!("{:.6}", r.loan_note_exchange_rate),
+                    r.available_liquidity.clone(),
+                ]
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(())
+}
+
+/// Compare the current on-chain configuration of a margin pool against a proposed one
+/// and return the field names with their current and proposed values that differ.
+fn diff_pool_config(
+    current: &MarginPool,
+    proposed: &MarginPoolConfig,
+    pyth_price: &Pubkey,
+) -> Vec<(&'static str, String, String)> {
+    let old = &current.config;
+    let fields = [
+        ("flags", old.flags.to_string(), proposed.flags.to_string()),
+        (
+            "utilization_rate_1",
+            old.utilization_rate_1.to_string(),
+            proposed.utilization_rate_1.to_string(),
+        ),
+        (
+            "utilization_rate_2",
+            old.utilization_rate_2.to_string(),
+            proposed.utilization_rate_2.to_string(),
+        ),
+        (
+            "borrow_rate_0",
+            old.borrow_rate_0.to_string(),
+            proposed.borrow_rate_0.to_string(),
+        ),
+        (
+            "borrow_rate_1",
+            old.borrow_rate_1.to_string(),
+            proposed.borrow_rate_1.to_string(),
+        ),
+        (
+            "borrow_rate_2",
+            old.borrow_rate_2.to_string(),
+            proposed.borrow_rate_2.to_string(),
+        ),
+        (
+            "borrow_rate_3",
+            old.borrow_rate_3.to_string(),
+            proposed.borrow_rate_3.to_string(),
+        ),
+        (
+            "management_fee_rate",
+            old.management_fee_rate.to_string(),
+            proposed.management_fee_rate.to_string(),
+        ),
+        (
+            "management_fee_collect_threshold",
+            old.management_fee_collect_threshold.to_string(),
+            proposed.management_fee_collect_threshold.to_string(),
+        ),
+        (
+            "token_price_oracle",
+            current.token_price_oracle.to_string(),
+            pyth_price.to_string(),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .collect()
+}
+
+/// The function handler to allow users to repay an outstanding loan to a margin pool.
+fn process_repay(
+    cfg: &Config,
+    amount: &TokenAmount,
+    base_units: bool,
+    margin_account: &Pubkey,
+    deposit_account: &Pubkey,
+    loan_account: &Pubkey,
+    margin_pool: &Pubkey,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    assert_exists!(&program, MarginAccount, margin_account);
+    assert_exists!(&program, MarginPool, margin_pool);
+
+    let pool = program.account::<MarginPool>(*margin_pool)?;
+
+    let (repay_ix, repay_name) = margin_repay_instruction(
+        cfg,
+        &program,
+        amount,
+        base_units,
+        margin_account,
+        deposit_account,
+        loan_account,
+        margin_pool,
+        &pool,
+    )?;
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .instruction(adapter_invoke_instruction(
+                cfg,
+                &signer.pubkey(),
+                margin_account,
+                repay_ix,
+            )?)
+            .signer(signer.as_ref()),
+        vec![adapter_invoke_name(repay_name, true).as_str()],
+    )
+}
+
+/// The function handler to allow users to withdraw deposited token funds from a margin pool.
+fn process_withdraw(
+    cfg: &Config,
+    amount: &TokenAmount,
+    base_units: bool,
+    destination_account: &Option<Pubkey>,
+    margin_pool: &Pubkey,
+    source_account: &Option<Pubkey>,
+    via_margin: &Option<Pubkey>,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    assert_exists!(&program, MarginPool, margin_pool);
+
+    let pool = program.account::<MarginPool>(*margin_pool)?;
+
+    // Withdrawing through a margin account burns the deposit notes held in its position
+    let depositor = via_margin.unwrap_or_else(|| signer.pubkey());
+    let source = match (source_account, via_margin) {
+        (Some(s), _) => *s,
+        (None, Some(m)) => find_position_token_account(cfg, m, &pool.deposit_note_mint),
+        (None, None) => return Err(anyhow!("either `--source` or `--via-margin` is required")),
+    };
+    let destination = destination_account
+        .unwrap_or_else(|| get_associated_token_address(&signer.pubkey(), &pool.token_mint));
+
+    assert_exists!(&program, TokenAccount, &source);
+
+    let (withdraw_ix, withdraw_name) = withdraw_instruction(
+        cfg,
+        &program,
+        amount,
+        base_units,
+        &depositor,
+        &destination,
+        margin_pool,
+        &pool,
+        &source,
+    )?;
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .instruction(invoke_through_margin(
+                cfg,
+                &signer.pubkey(),
+                via_margin,
+                withdraw_ix,
+            )?)
+            .signer(signer.as_ref()),
+        vec![adapter_invoke_name(withdraw_name, via_margin.is_some()).as_str()],
+    )
+}
+
+/// The number of basis points in a whole unit for rates and percentages.
+const BPS_PER_UNIT: f64 = 10_000.0;
+
+/// The fixed-point precision of the `jet_proto_math::Number` values stored in pool accounts.
+const NUMBER_PRECISION: f64 = 1e15;
+
+/// Compound an annual percentage rate that is accrued continuously into an annual percentage yield.
+fn apr_to_apy(apr: f64) -> f64 {
+    apr.exp_m1()
+}
+
+/// Calculate the borrow rate of a pool for the argued utilization by linearly
+/// interpolating between the points of the pool's configured rate curve.
+fn borrow_rate(utilization: f64, config: &MarginPoolConfig) -> f64 {
+    let util_1 = f64::from(config.utilization_rate_1) / BPS_PER_UNIT;
+    let util_2 = f64::from(config.utilization_rate_2) / BPS_PER_UNIT;
+    let rate_0 = f64::from(config.borrow_rate_0) / BPS_PER_UNIT;
+    let rate_1 = f64::from(config.borrow_rate_1) / BPS_PER_UNIT;
+    let rate_2 = f64::from(config.borrow_rate_2) / BPS_PER_UNIT;
+    let rate_3 = f64::from(config.borrow_rate_3) / BPS_PER_UNIT;
+
+    let interpolate = |x: f64, x0: f64, x1: f64, y0: f64, y1: f64| {
+        if x1 <= x0 {
+            y1
+        } else {
+            y0 + (x - x0) * (y1 - y0) / (x1 - x0)
+        }
+    };
+
+    if utilization <= util_1 {
+        interpolate(utilization, 0.0, util_1, rate_0, rate_1)
+    } else if utilization <= util_2 {
+        interpolate(utilization, util_1, util_2, rate_1, rate_2)
+    } else {
+        interpolate(utilization, util_2, 1.0, rate_2, rate_3)
+    }
+}
+
+/// Calculate the number of tokens that each note is worth, defaulting to one when there are no notes.
+fn exchange_rate(tokens: f64, notes: u64) -> f64 {
+    if notes == 0 {
+        1.0
+    } else {
+        tokens / notes as f64
+    }
+}
+
+/// Format a unit ratio as a percentage with two decimal places.
+fn format_percent(ratio: f64) -> String {
+    format!("{:.2}%", ratio * 100.0)
+}
+
+/// Convert the little-endian bytes of a `jet_proto_math::Number` into a floating point value.
+fn number_to_f64(bytes: &[u8; 24]) -> f64 {
+    bytes
+        .chunks_exact(8)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut limb = [0u8; 8];
+            limb.copy_from_slice(chunk);
+            u64::from_le_bytes(limb) as f64 * 2f64.powi(64 * i as i32)
+        })
+        .sum::<f64>()
+        / NUMBER_PRECISION
+}
+
+/// Prefix the approval name of a margin pool instruction with the
+/// margin program instruction that wraps it, if there is one.
+fn adapter_invoke_name(name: String, via_margin: bool) -> String {
+    if via_margin {
+        format!("jet_margin::AdapterInvoke -> {}", name)
+    } else {
+        name
+    }
+}
+
+/// Find the margin pool from either its explicit public key or by
+/// deriving it from the token mint, if either was provided.
+fn find_margin_pool(
+    cfg: &Config,
+    pool: &Option<Pubkey>,
+    token_mint: &Option<TokenMint>,
+) -> Result<Option<Pubkey>> {
+    match (pool, token_mint) {
+        (Some(p), _) => Ok(Some(*p)),
+        (None, Some(tm)) => Ok(Some(derive_margin_pool(
+            &cfg.tokens.resolve(tm)?,
+            &cfg.program_id,
+        ))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Find the position token account of a margin account for the argued note mint
+/// and build the instruction to register the position if it does not yet exist.
+fn find_or_register_position(
+    cfg: &Config,
+    program: &Program,
+    authority: &Pubkey,
+    margin_account: &Pubkey,
+    note_mint: &Pubkey,
+) -> Result<(Pubkey, Option<Instruction>)> {
+    let position = find_position_token_account(cfg, margin_account, note_mint);
+
+    if account_exists(program, &position)? {
+        return Ok((position, None));
+    }
+
+    let ix = register_position_instruction(cfg, authority, margin_account, note_mint)?;
+    Ok((position, Some(ix)))
+}
+
+/// Wrap a margin pool instruction in `jet_margin::AdapterInvoke` if it is
+/// being invoked through a margin account, otherwise return it unchanged.
+fn invoke_through_margin(
+    cfg: &Config,
+    owner: &Pubkey,
+    via_margin: &Option<Pubkey>,
+    pool_ix: Instruction,
+) -> Result<Instruction> {
+    match via_margin {
+        Some(margin_account) => adapter_invoke_instruction(cfg, owner, margin_account, pool_ix),
+        None => Ok(pool_ix),
+    }
+}
+
+/// Build the `jet_margin_pool::MarginRepay` instruction to repay the loan of a margin account
+/// along with its name for approval. Repaying the entire loan is denominated in loan notes
+/// rather than tokens so that no dust remains from accrued interest.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn margin_repay_instruction(
+    cfg: &Config,
+    program: &Program,
+    amount: &TokenAmount,
+    base_units: bool,
+    margin_account: &Pubkey,
+    deposit_account: &Pubkey,
+    loan_account: &Pubkey,
+    margin_pool: &Pubkey,
+    pool: &MarginPool,
+) -> Result<(Instruction, String)> {
+    cfg.tokens.verify_symbol(amount, &pool.token_mint)?;
+    let decimals = mint_decimals(program, &pool.token_mint)?;
+    let (max_amount, name) = match amount.to_base_units(decimals, base_units)? {
+        Some(amt) => (
+            Amount::tokens(amt),
+            format!(
+                "jet_margin_pool::MarginRepay {}",
+                UiAmount::new(amt, decimals).with_symbol(cfg.tokens.symbol(&pool.token_mint))
+            ),
+        ),
+        None => {
+            let notes = program.account::<TokenAccount>(*loan_account)?.amount;
+            (
+                Amount::notes(notes),
+                format!("jet_margin_pool::MarginRepay all ({} loan notes)", notes),
+            )
+        }
+    };
+
+    let ix = build_instruction(
+        &program.id(),
+        accounts::MarginRepay {
+            margin_account: *margin_account,
+            margin_pool: *margin_pool,
+            loan_note_mint: pool.loan_note_mint,
+            deposit_note_mint: pool.deposit_note_mint,
+            loan_account: *loan_account,
+            deposit_account: *deposit_account,
+            token_program,
+        },
+        instruction::MarginRepay { max_amount },
+    );
+
+    Ok((ix, name))
+}
+
+/// Resolve the target margin pool from either its explicit
+/// public key or by deriving it from the token mint.
+fn resolve_margin_pool(
+    cfg: &Config,
+    pool: &Option<Pubkey>,
+    token_mint: &Option<TokenMint>,
+) -> Result<Pubkey> {
+    find_margin_pool(cfg, pool, token_mint)?
+        .ok_or_else(|| anyhow!("either `--pool` or `--token-mint` is required"))
+}
+
+/// Resolve the margin account selected to invoke a margin pool instruction
+/// through if `--via-margin` was set, otherwise returning `None`.
+fn resolve_via_margin(
+    cfg: &Config,
+    account: &MarginAccountSelector,
+    via_margin: bool,
+) -> Result<Option<Pubkey>> {
+    if !via_margin {
+        return Ok(None);
+    }
+
+    account.find(cfg)?.map(Some).ok_or_else(|| {
+        anyhow!("`--via-margin` requires a margin account through `--account` or `--seed`")
+    })
+}
+
+/// Build the `jet_margin_pool::Withdraw` instruction to withdraw deposited funds from a
+/// margin pool along with its name for approval. Withdrawing everything burns the full
+/// deposit note balance of the source account.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn withdraw_instruction(
+    cfg: &Config,
+    program: &Program,
+    amount: &TokenAmount,
+    base_units: bool,
+    depositor: &Pubkey,
+    destination: &Pubkey,
+    margin_pool: &Pubkey,
+    pool: &MarginPool,
+    source: &Pubkey,
+) -> Result<(Instruction, String)> {
+    cfg.tokens.verify_symbol(amount, &pool.token_mint)?;
+    let decimals = mint_decimals(program, &pool.token_mint)?;
+    let (amount, name) = match amount.to_base_units(decimals, base_units)? {
+        Some(amt) => (
+            Amount::tokens(amt),
+            format!(
+                "jet_margin_pool::Withdraw {}",
+                UiAmount::new(amt, decimals).with_symbol(cfg.tokens.symbol(&pool.token_mint))
+            ),
+        ),
+        None => {
+            let notes = program.account::<TokenAccount>(*source)?.amount;
+            (
+                Amount::notes(notes),
+                format!("jet_margin_pool::Withdraw all ({} deposit notes)", notes),
+            )
+        }
+    };
+
+    let ix = build_instruction(
+        &program.id(),
+        accounts::Withdraw {
+            depositor: *depositor,
+            margin_pool: *margin_pool,
+            vault: pool.vault,
+            deposit_note_mint: pool.deposit_note_mint,
+            source: *source,
+            destination: *destination,
+            token_program,
+        },
+        instruction::Withdraw { amount },
+    );
+
+    Ok((ix, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_TOML: &str = r#"
+flags = 1
+utilization_rate_1 = 8500
+utilization_rate_2 = 9500
+borrow_rate_0 = 50
+borrow_rate_1 = 600
+borrow_rate_2 = 4000
+borrow_rate_3 = 16000
+management_fee_rate = 1000
+management_fee_collect_threshold = 100
+
+[oracle]
+product = "11111111111111111111111111111111"
+price = "SysvarRent111111111111111111111111111111111"
+"#;
+
+    const SAMPLE_JSON: &str = r#"{
+    "flags": 1,
+    "utilization_rate_1": 8500,
+    "utilization_rate_2": 9500,
+    "borrow_rate_0": 50,
+    "borrow_rate_1": 600,
+    "borrow_rate_2": 4000,
+    "borrow_rate_3": 16000,
+    "management_fee_rate": 1000,
+    "management_fee_collect_threshold": 100,
+    "oracle": {
+        "product": "11111111111111111111111111111111",
+        "price": "SysvarRent111111111111111111111111111111111"
+    }
+}"#;
+
+    #[test]
+    fn pool_config_file_formats_match() {
+        let from_toml: PoolConfigFile = toml::from_str(SAMPLE_TOML).unwrap();
+        let from_json: PoolConfigFile = serde_json::from_str(SAMPLE_JSON).unwrap();
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_toml.to_pool_config().borrow_rate_3, 16000);
+    }
+
+    #[test]
+    fn borrow_rate_follows_curve() {
+        let file: PoolConfigFile = toml::from_str(SAMPLE_TOML).unwrap();
+        let config = file.to_pool_config();
+
+        assert!((borrow_rate(0.0, &config) - 0.005).abs() < 1e-9);
+        assert!((borrow_rate(0.85, &config) - 0.06).abs() < 1e-9);
+        assert!((borrow_rate(0.9, &config) - 0.23).abs() < 1e-9);
+        assert!((borrow_rate(1.0, &config) - 1.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn number_bytes_conversion() {
+        let mut bytes = [0u8; 24];
+        bytes[..8].copy_from_slice(&2_500_000_000_000_000u64.to_le_bytes());
+        assert!((number_to_f64(&bytes) - 2.5).abs() < 1e-9);
+        assert_eq!(number_to_f64(&[0u8; 24]), 0.0);
+    }
+
+    #[test]
+    fn rate_conversions() {
+        assert!((apr_to_apy(0.1) - 0.105170918).abs() < 1e-9);
+        assert_eq!(exchange_rate(100.0, 0), 1.0);
+        assert_eq!(exchange_rate(150.0, 100), 1.5);
+        assert_eq!(format_percent(0.12346), "12.35%");
+    }
+
+    #[test]
+    fn pool_config_file_oracle_keys() {
+        let file: PoolConfigFile = toml::from_str(SAMPLE_TOML).unwrap();
+        let (product, price) = file.oracle_keys().unwrap();
+        assert_eq!(product, system_program);
+        assert_eq!(price, rent);
+    }
+}
@@ -0,0 +1,618 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_client::solana_sdk::system_program::ID as system_program;
+use anchor_client::Program;
+use anyhow::{anyhow, Result};
+use clap::{Subcommand, ValueEnum};
+use jet_metadata::{
+    accounts, instruction, LiquidatorMetadata, MarginAdapterMetadata, PositionTokenMetadata,
+    TokenMetadata,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::config::{parse_address, Config, JetProgram, Overrides};
+use crate::macros::{assert_exists, assert_not_exists};
+use crate::program::{
+    build_instruction, create_program_client, create_read_only_client, send_with_approval,
+};
+use crate::pubkey::derive_metadata_entry;
+use crate::terminal::{print_serialized, DisplayOptions};
+
+/// The size in bytes of the Anchor account discriminator that
+/// prefixes the data of every metadata program account.
+const DISCRIMINATOR_SIZE: u64 = 8;
+
+/// The types of metadata program accounts that can be managed.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum MetadataKind {
+    /// `jet_metadata::MarginAdapterMetadata`
+    Adapter,
+    /// `jet_metadata::LiquidatorMetadata`
+    Liquidator,
+    /// `jet_metadata::PositionTokenMetadata`
+    PositionToken,
+    /// `jet_metadata::TokenMetadata`
+    Token,
+}
+
+impl MetadataKind {
+    /// The Anchor account discriminator of the metadata account type.
+    fn discriminator(&self) -> [u8; 8] {
+        match self {
+            Self::Adapter => MarginAdapterMetadata::discriminator(),
+            Self::Liquidator => LiquidatorMetadata::discriminator(),
+            Self::PositionToken => PositionTokenMetadata::discriminator(),
+            Self::Token => TokenMetadata::discriminator(),
+        }
+    }
+
+    /// The space in bytes required for the fields of the metadata account type.
+    fn space(&self) -> u64 {
+        let size = match self {
+            Self::Adapter => std::mem::size_of::<MarginAdapterMetadata>(),
+            Self::Liquidator => std::mem::size_of::<LiquidatorMetadata>(),
+            Self::PositionToken => std::mem::size_of::<PositionTokenMetadata>(),
+            Self::Token => std::mem::size_of::<TokenMetadata>(),
+        };
+        size as u64
+    }
+}
+
+/// Utility struct for serialization of a metadata program
+/// account and its fields for display purposes.
+#[derive(Debug, Serialize)]
+struct MetadataEntry {
+    address: String,
+    kind: String,
+    fields: BTreeMap<&'static str, String>,
+}
+
+/// Trait for listing the displayable fields of the metadata account types.
+trait MetadataFields {
+    fn fields(&self) -> Vec<(&'static str, String)>;
+}
+
+impl MetadataFields for LiquidatorMetadata {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![("liquidator", self.liquidator.to_string())]
+    }
+}
+
+impl MetadataFields for MarginAdapterMetadata {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![("adapter_program", self.adapter_program.to_string())]
+    }
+}
+
+impl MetadataFields for PositionTokenMetadata {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("position_token_mint", self.position_token_mint.to_string()),
+            (
+                "underlying_token_mint",
+                self.underlying_token_mint.to_string(),
+            ),
+            ("adapter_program", self.adapter_program.to_string()),
+            ("token_kind", format!("{:?}", self.token_kind)),
+            ("collateral_weight", self.collateral_weight.to_string()),
+        ]
+    }
+}
+
+impl MetadataFields for TokenMetadata {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("token_mint", self.token_mint.to_string()),
+            ("pyth_price", self.pyth_price.to_string()),
+            ("pyth_product", self.pyth_product.to_string()),
+        ]
+    }
+}
+
+/// Metadata program based subcommand enum variants.
+#[derive(Debug, Subcommand)]
+pub enum MetadataCommand {
+    /// Create a new metadata account entry as the metadata authority.
+    Create {
+        /// The type of metadata account to create.
+        #[clap(value_enum)]
+        kind: MetadataKind,
+        /// Hex encoded data of the account fields to write after creation.
+        #[clap(long, value_parser)]
+        data: Option<String>,
+        /// Base-58 public key of the account the entry is keyed by.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        key: Pubkey,
+        /// The string seed of the entry.
+        #[clap(long, value_parser, default_value = "")]
+        seed: String,
+    },
+    /// Get the account data for a metadata account entry.
+    Get {
+        /// The type of metadata account to get.
+        #[clap(value_enum)]
+        kind: MetadataKind,
+        /// Base-58 public key of the metadata account.
+        #[clap(value_parser = parse_address, required_unless_present = "key")]
+        address: Option<Pubkey>,
+        /// Output data as serialized JSON.
+        #[clap(long, value_parser)]
+        json: bool,
+        /// Base-58 public key of the account the entry is keyed by.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", conflicts_with = "address")]
+        key: Option<Pubkey>,
+        /// Formatted data output.
+        #[clap(long, value_parser)]
+        pretty: bool,
+        /// The string seed of the entry.
+        #[clap(long, value_parser, default_value = "")]
+        seed: String,
+    },
+    /// List all existing metadata accounts of a type.
+    List {
+        /// The type of metadata accounts to list.
+        #[clap(value_enum)]
+        kind: MetadataKind,
+        /// Output data as serialized JSON.
+        #[clap(long, value_parser)]
+        json: bool,
+        /// Formatted data output.
+        #[clap(long, value_parser)]
+        pretty: bool,
+    },
+    /// Remove a metadata account entry as the metadata authority.
+    Remove {
+        /// The type of metadata account to remove.
+        #[clap(value_enum)]
+        kind: MetadataKind,
+        /// Base-58 public key of the account the entry is keyed by.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        key: Pubkey,
+        /// The public key to receive the rent.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        receiver: Option<Pubkey>,
+        /// The string seed of the entry.
+        #[clap(long, value_parser, default_value = "")]
+        seed: String,
+    },
+    /// Overwrite the data of a metadata account entry as the metadata authority.
+    Set {
+        /// The type of metadata account to overwrite.
+        #[clap(value_enum)]
+        kind: MetadataKind,
+        /// Hex encoded data to write into the account fields.
+        #[clap(long, value_parser)]
+        data: String,
+        /// Base-58 public key of the account the entry is keyed by.
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        key: Pubkey,
+        /// Byte offset to write at relative to the start of the account fields.
+        #[clap(long, value_parser, default_value_t = 0)]
+        offset: u64,
+        /// The string seed of the entry.
+        #[clap(long, value_parser, default_value = "")]
+        seed: String,
+    },
+}
+
+/// The main entry point and handler for all metadata
+/// program interaction commands.
+pub fn entry(
+    overrides: &Overrides,
+    program_id: &Option<Pubkey>,
+    subcmd: &MetadataCommand,
+) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::Metadata, *program_id)?;
+    match subcmd {
+        MetadataCommand::Create {
+            kind,
+            data,
+            key,
+            seed,
+        } => process_create(&cfg, kind, data, key, seed),
+        MetadataCommand::Get {
+            kind,
+            address,
+            json,
+            key,
+            pretty,
+            seed,
+        } => process_get(
+            &cfg,
+            kind,
+            &address.unwrap_or_else(|| {
+                derive_metadata_entry(&key.unwrap_or_default(), seed, &cfg.program_id)
+            }),
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
+        MetadataCommand::List { kind, json, pretty } => process_list(
+            &cfg,
+            kind,
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
+        MetadataCommand::Remove {
+            kind,
+            key,
+            receiver,
+            seed,
+        } => process_remove(&cfg, kind, key, receiver, seed),
+        MetadataCommand::Set {
+            kind,
+            data,
+            key,
+            offset,
+            seed,
+        } => process_set(&cfg, kind, data, key, *offset, seed),
+    }
+}
+
+/// The function handler for the metadata authority to create a new metadata account entry
+/// and write its account discriminator along with any provided field data.
+fn process_create(
+    cfg: &Config,
+    kind: &MetadataKind,
+    data: &Option<String>,
+    key: &Pubkey,
+    seed: &str,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    let metadata_account = derive_metadata_entry(key, seed, &program.id());
+    match kind {
+        MetadataKind::Adapter => {
+            assert_not_exists!(&program, MarginAdapterMetadata, &metadata_account)
+        }
+        MetadataKind::Liquidator => {
+            assert_not_exists!(&program, LiquidatorMetadata, &metadata_account)
+        }
+        MetadataKind::PositionToken => {
+            assert_not_exists!(&program, PositionTokenMetadata, &metadata_account)
+        }
+        MetadataKind::Token => assert_not_exists!(&program, TokenMetadata, &metadata_account),
+    }
+
+    let mut entry_data = kind.discriminator().to_vec();
+    if let Some(d) = data {
+        entry_data.extend(parse_hex(d)?);
+    }
+
+    if entry_data.len() as u64 > DISCRIMINATOR_SIZE + kind.space() {
+        return Err(anyhow!(
+            "data exceeds the {} bytes of the {:?} metadata fields",
+            kind.space(),
+            kind
+        ));
+    }
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .instruction(build_instruction(
+                &program.id(),
+                accounts::CreateEntry {
+                    key_account: *key,
+                    metadata_account,
+                    authority: signer.pubkey(),
+                    payer: cfg.fee_payer.pubkey(),
+                    system_program,
+                },
+                instruction::CreateEntry {
+                    seed: seed.to_owned(),
+                    space: DISCRIMINATOR_SIZE + kind.space(),
+                },
+            ))
+            .accounts(accounts::SetEntry {
+                metadata_account,
+                authority: signer.pubkey(),
+            })
+            .args(instruction::SetEntry {
+                offset: 0,
+                data: entry_data,
+            })
+            .signer(signer.as_ref()),
+        vec!["jet_metadata::CreateEntry", "jet_metadata::SetEntry"],
+    )?;
+
+    println!("Pubkey: {}", metadata_account);
+
+    Ok(())
+}
+
+/// The function handler to get the deserialized data of a metadata account
+/// and display it in the terminal for the user to observe or parse.
+fn process_get(
+    cfg: &Config,
+    kind: &MetadataKind,
+    address: &Pubkey,
+    display: DisplayOptions,
+) -> Result<()> {
+    let program = create_read_only_client(cfg);
+    print_serialized(fetch_kind_entry(&program, kind, address)?, &display)
+}
+
+/// The function handler to fetch and display all existing metadata accounts of a type.
+fn process_list(cfg: &Config, kind: &MetadataKind, display: DisplayOptions) -> Result<()> {
+    let program = create_read_only_client(cfg);
+
+    let entries = match kind {
+        MetadataKind::Adapter => list_entries::<MarginAdapterMetadata>(&program, kind)?,
+        MetadataKind::Liquidator => list_entries::<LiquidatorMetadata>(&program, kind)?,
+        MetadataKind::PositionToken => list_entries::<PositionTokenMetadata>(&program, kind)?,
+        MetadataKind::Token => list_entries::<TokenMetadata>(&program, kind)?,
+    };
+
+    print_serialized(entries, &display)
+}
+
+/// The function handler for the metadata authority to remove
+/// a metadata account entry and receive back the rent.
+fn process_remove(
+    cfg: &Config,
+    kind: &MetadataKind,
+    key: &Pubkey,
+    receiver: &Option<Pubkey>,
+    seed: &str,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    // Fetching the entry asserts that it exists as the argued kind
+    let metadata_account = derive_metadata_entry(key, seed, &program.id());
+    fetch_kind_entry(&program, kind, &metadata_account)?;
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .accounts(accounts::RemoveEntry {
+                key_account: *key,
+                metadata_account,
+                authority: signer.pubkey(),
+                receiver: receiver.unwrap_or(signer.pubkey()),
+            })
+            .args(instruction::RemoveEntry {})
+            .signer(signer.as_ref()),
+        vec!["jet_metadata::RemoveEntry"],
+    )
+}
+
+/// The function handler for the metadata authority to overwrite the field data of a
+/// metadata account entry at the argued offset after reviewing the changed fields.
+fn process_set(
+    cfg: &Config,
+    kind: &MetadataKind,
+    data: &str,
+    key: &Pubkey,
+    offset: u64,
+    seed: &str,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
+
+    let metadata_account = derive_metadata_entry(key, seed, &program.id());
+    fetch_kind_entry(&program, kind, &metadata_account)?;
+
+    // Decode the fields of the current and the overwritten account
+    // data to review the changes before requesting approval
+    let patch = parse_hex(data)?;
+    let current = program.rpc().get_account_data(&metadata_account)?;
+    let updated = overwrite_fields(kind, &current, offset, &patch)?;
+
+    let changes: Vec<_> = decode_fields(kind, &current)?
+        .into_iter()
+        .zip(decode_fields(kind, &updated)?)
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| (field, old, new))
+        .collect();
+
+    if changes.is_empty() {
+        println!(
+            "No changes to the fields of metadata entry {}",
+            metadata_account
+        );
+        return Ok(());
+    }
+
+    println!("Field changes for metadata entry {}:", metadata_account);
+    for (field, old, new) in &changes {
+        println!("  {}: {} -> {}", field, old, new);
+    }
+    println!();
+
+    send_with_approval(
+        cfg,
+        program
+            .request()
+            .accounts(accounts::SetEntry {
+                metadata_account,
+                authority: signer.pubkey(),
+            })
+            .args(instruction::SetEntry {
+                offset: DISCRIMINATOR_SIZE + offset,
+                data: patch,
+            })
+            .signer(signer.as_ref()),
+        vec!["jet_metadata::SetEntry"],
+    )
+}
+
+/// Fetch and deserialize a single metadata account of the generic type.
+fn fetch_entry<T>(program: &Program, kind: &MetadataKind, address: &Pubkey) -> Result<MetadataEntry>
+where
+    T: AccountDeserialize + MetadataFields,
+{
+    assert_exists!(program, T, address);
+    let account = program.account::<T>(*address)?;
+    Ok(to_entry(address, kind, &account))
+}
+
+/// Fetch and deserialize a single metadata account of the argued kind.
+fn fetch_kind_entry(
+    program: &Program,
+    kind: &MetadataKind,
+    address: &Pubkey,
+) -> Result<MetadataEntry> {
+    match kind {
+        MetadataKind::Adapter => fetch_entry::<MarginAdapterMetadata>(program, kind, address),
+        MetadataKind::Liquidator => fetch_entry::<LiquidatorMetadata>(program, kind, address),
+        MetadataKind::PositionToken => fetch_entry::<PositionTokenMetadata>(program, kind, address),
+        MetadataKind::Token => fetch_entry::<TokenMetadata>(program, kind, address),
+    }
+}
+
+/// Decode the displayable fields of the data of a metadata account of the argued kind,
+/// returning an error if its discriminator does not match the kind.
+fn decode_fields(kind: &MetadataKind, data: &[u8]) -> Result<Vec<(&'static str, String)>> {
+    fn decode<T: AccountDeserialize + MetadataFields>(
+        mut data: &[u8],
+    ) -> Result<Vec<(&'static str, String)>> {
+        Ok(T::try_deserialize(&mut data)?.fields())
+    }
+
+    match kind {
+        MetadataKind::Adapter => decode::<MarginAdapterMetadata>(data),
+        MetadataKind::Liquidator => decode::<LiquidatorMetadata>(data),
+        MetadataKind::PositionToken => decode::<PositionTokenMetadata>(data),
+        MetadataKind::Token => decode::<TokenMetadata>(data),
+    }
+}
+
+/// Overwrite the account data of a metadata entry with the argued bytes at the offset
+/// relative to its fields, which must be written within the fields of the kind.
+fn overwrite_fields(
+    kind: &MetadataKind,
+    data: &[u8],
+    offset: u64,
+    patch: &[u8],
+) -> Result<Vec<u8>> {
+    let end = offset
+        .checked_add(patch.len() as u64)
+        .filter(|end| *end <= kind.space())
+        .ok_or_else(|| {
+            anyhow!(
+                "{} bytes at offset {} exceed the {} bytes of the {:?} metadata fields",
+                patch.len(),
+                offset,
+                kind.space(),
+                kind
+            )
+        })?;
+
+    let mut updated = data.to_vec();
+    updated
+        .get_mut((DISCRIMINATOR_SIZE + offset) as usize..(DISCRIMINATOR_SIZE + end) as usize)
+        .ok_or_else(|| {
+            anyhow!(
+                "the metadata account data is smaller than its {:?} fields",
+                kind
+            )
+        })?
+        .copy_from_slice(patch);
+
+    Ok(updated)
+}
+
+/// Fetch and deserialize all existing metadata accounts of the generic type.
+fn list_entries<T>(program: &Program, kind: &MetadataKind) -> Result<Vec<MetadataEntry>>
+where
+    T: AccountDeserialize + Discriminator + MetadataFields,
+{
+    Ok(program
+        .accounts::<T>(vec![])?
+        .iter()
+        .map(|(address, account)| to_entry(address, kind, account))
+        .collect())
+}
+
+/// Parse a hex encoded string, with or without a `0x` prefix, into bytes.
+fn parse_hex(s: &str) -> Result<Vec<u8>> {
+    let hex = s.trim().trim_start_matches("0x");
+
+    if hex.len() % 2 != 0 {
+        return Err(anyhow!("hex data must have an even number of digits"));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| anyhow!("invalid hex data: {}", s))
+        })
+        .collect()
+}
+
+/// Convert a deserialized metadata account into its displayable entry.
+fn to_entry(address: &Pubkey, kind: &MetadataKind, account: &impl MetadataFields) -> MetadataEntry {
+    MetadataEntry {
+        address: address.to_string(),
+        kind: format!("{:?}", kind),
+        fields: account.fields().into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_data() {
+        assert_eq!(parse_hex("0x00ff10").unwrap(), vec![0, 255, 16]);
+        assert_eq!(parse_hex("ABcd").unwrap(), vec![171, 205]);
+        assert_eq!(parse_hex("").unwrap(), Vec::<u8>::new());
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("zz").is_err());
+        assert!(parse_hex("é0").is_err());
+    }
+
+    #[test]
+    fn overwrite_metadata_fields() {
+        let kind = MetadataKind::Liquidator;
+        let mut data = kind.discriminator().to_vec();
+        data.extend([0; 32]);
+
+        let liquidator = Pubkey::new_unique();
+        let updated = overwrite_fields(&kind, &data, 0, liquidator.as_ref()).unwrap();
+        assert_eq!(
+            decode_fields(&kind, &updated).unwrap(),
+            vec![("liquidator", liquidator.to_string())]
+        );
+
+        assert!(overwrite_fields(&kind, &data, 1, liquidator.as_ref()).is_err());
+        assert!(overwrite_fields(&kind, &data, u64::MAX, &[1]).is_err());
+        assert!(decode_fields(&MetadataKind::Token, &updated).is_err());
+    }
+
+    #[test]
+    fn metadata_entry_fields() {
+        let entry = to_entry(
+            &Pubkey::default(),
+            &MetadataKind::Liquidator,
+            &LiquidatorMetadata {
+                liquidator: Pubkey::default(),
+            },
+        );
+
+        assert_eq!(entry.kind, "Liquidator");
+        assert_eq!(
+            entry.fields.get("liquidator").unwrap(),
+            "11111111111111111111111111111111"
+        );
+    }
+}
@@ -15,6 +15,11 @@
 
 pub mod airdrop;
 pub mod auth;
+pub mod completions;
+pub mod config;
+pub mod labels;
+pub mod man;
 pub mod margin;
 pub mod margin_pool;
+pub mod metadata;
 pub mod staking;
@@ -21,65 +21,78 @@ use anchor_client::solana_sdk::system_program::ID as system_program;
 use anchor_client::solana_sdk::sysvar::rent::ID as rent;
 use anchor_spl::associated_token::get_associated_token_address;
 use anchor_spl::token::ID as token_program;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Subcommand;
 use jet_staking::state::{StakeAccount, StakePool};
 use jet_staking::{accounts, instruction, PoolConfig};
+use std::str::FromStr;
 
-use crate::config::{Config, Overrides};
+use crate::amount::{mint_decimals, TokenAmount, UiAmount};
+use crate::config::{parse_address, Config, JetProgram, Overrides};
 use crate::macros::*;
 use crate::program::*;
 use crate::pubkey::*;
 use crate::terminal::{print_serialized, DisplayOptions, Spinner};
+use crate::token::TokenMint;
 
 pub const DEFAULT_STAKE_POOL: &str = "4o7XLNe2NYtcxhFpiXYKSobgodsuQvHgxKriDiYqE2tP";
 
+/// Resolve the argued stake pool, falling back to the `stake_pool`
+/// of the configuration profile and then `DEFAULT_STAKE_POOL`.
+pub fn resolve_stake_pool(cfg: &Config, pool: &Option<Pubkey>) -> Pubkey {
+    pool.or(cfg.stake_pool)
+        .unwrap_or_else(|| Pubkey::from_str(DEFAULT_STAKE_POOL).unwrap())
+}
+
 /// Staking program based subcommand enum variants.
 #[derive(Debug, Subcommand)]
 pub enum StakingCommand {
     /// Get the account data for user's stake account.
     Account {
         /// Base-58 pubkey of the account.
-        #[clap(value_parser)]
+        #[clap(value_parser = parse_address)]
         address: Option<Pubkey>,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
         json: bool,
         /// Base-58 pubkey of the account owner.
-        #[clap(long, value_parser, conflicts_with = "address")]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", conflicts_with = "address")]
         owner: Option<Pubkey>,
         /// The stake pool associated with the account.
-        #[clap(long, value_parser, conflicts_with = "address", default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY", conflicts_with = "address")]
+        pool: Option<Pubkey>,
         /// Formatted data output.
         #[clap(long, value_parser)]
         pretty: bool,
     },
     /// Deposit to a stake pool from your account.
     Add {
-        /// The amount of token to stake in the pool. The program
-        /// by default will attempt to stake as much as possible if
-        /// no amount is provided.
+        /// The amount of token to stake in the pool (e.g. `1.5` or `1.5 JET`).
+        /// The program by default will attempt to stake as much as possible
+        /// if no amount or `max` is provided.
+        #[clap(long, value_parser)]
+        amount: Option<TokenAmount>,
+        /// Interpret the amount as a raw value in the mint's base units.
         #[clap(long, value_parser)]
-        amount: Option<u64>,
+        base_units: bool,
         /// Stake pool to deposit.
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Option<Pubkey>,
     },
     /// Close a stake account.
     CloseAccount {
         /// Stake pool associated with the account.
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Option<Pubkey>,
         /// Wallet receiving the rent funds.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         receiver: Option<Pubkey>,
     },
     /// Create a new stake account.
     CreateAccount {
         /// Stake pool to associate the new account.
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Option<Pubkey>,
     },
     /// Create a new staking pool.
     CreatePool {
@@ -87,11 +100,11 @@ pub enum StakingCommand {
         #[clap(long, value_parser)]
         seed: String,
         /// Governance realm to associate with the new pool.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         realm: Pubkey,
         /// Token mint for the stake pool.
-        #[clap(long, value_parser)]
-        token_mint: Pubkey,
+        #[clap(long, value_parser, value_name = "MINT")]
+        token_mint: TokenMint,
         /// Unbonding period as u64.
         #[clap(long, value_parser)]
         unbond_period: u64,
@@ -99,11 +112,11 @@ pub enum StakingCommand {
     /// Derive the public key of a `jet_staking::StakeAccount`.
     DeriveAccount {
         /// Base-58 pubkey of the account owner.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         owner: Option<Pubkey>,
         /// Stake pool account to use.
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Option<Pubkey>,
     },
     /// Derive the public key of a `jet_staking::StakePool`.
     DerivePool {
@@ -117,8 +130,8 @@ pub enum StakingCommand {
     /// Get the account data for a stake pool.
     Pool {
         /// Base-58 public key of the pool.
-        #[clap(value_parser, default_value = DEFAULT_STAKE_POOL)]
-        address: Pubkey,
+        #[clap(value_parser = parse_address)]
+        address: Option<Pubkey>,
         /// Output data as serialized JSON.
         #[clap(long, value_parser)]
         json: bool,
@@ -128,37 +141,44 @@ pub enum StakingCommand {
     },
     /// Withdraw bonded stake funds from a pool.
     WithdrawBonded {
-        /// Amount of funds to withdraw.
+        /// Amount of funds to withdraw (e.g. `1.5` or `1.5 JET`).
+        #[clap(long, value_parser)]
+        amount: TokenAmount,
+        /// Interpret the amount as a raw value in the mint's base units.
         #[clap(long, value_parser)]
-        amount: u64,
+        base_units: bool,
         /// Stake pool to withdraw.
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Option<Pubkey>,
         /// Wallet to receive the withdrawn funds.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         receiver: Option<Pubkey>,
     },
     /// Withdraw bonded stake funds from a pool.
     WithdrawUnbonded {
         /// Stake pool to withdraw.
-        #[clap(long, value_parser, default_value = DEFAULT_STAKE_POOL)]
-        pool: Pubkey,
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
+        pool: Option<Pubkey>,
         /// Wallet to receive the account rent.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         rent_receiver: Option<Pubkey>,
         /// Wallet to receive withdrawn funds.
-        #[clap(long, value_parser)]
+        #[clap(long, value_parser = parse_address, value_name = "PUBKEY")]
         token_receiver: Option<Pubkey>,
         /// Public key of the unbonding account.
-        #[clap(long = "account", value_parser)]
+        #[clap(long = "account", value_parser = parse_address, value_name = "PUBKEY")]
         unbonding_account: Pubkey,
     },
 }
 
 /// The main entry point and handler for all staking
 /// program interaction commands.
-pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &StakingCommand) -> Result<()> {
-    let cfg = Config::new(overrides, *program_id)?;
+pub fn entry(
+    overrides: &Overrides,
+    program_id: &Option<Pubkey>,
+    subcmd: &StakingCommand,
+) -> Result<()> {
+    let cfg = Config::new(overrides, JetProgram::Staking, *program_id)?;
     match subcmd {
         StakingCommand::Account {
             address,
@@ -170,21 +190,35 @@ pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &StakingCommand
             &cfg,
             address,
             owner,
-            pool,
-            DisplayOptions::from_args(*json, *pretty),
+            &resolve_stake_pool(&cfg, pool),
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
         ),
-        StakingCommand::Add { amount, pool } => process_add_stake(&cfg, amount, pool),
+        StakingCommand::Add {
+            amount,
+            base_units,
+            pool,
+        } => process_add_stake(&cfg, amount, *base_units, &resolve_stake_pool(&cfg, pool)),
         StakingCommand::CloseAccount { pool, receiver } => {
-            process_close_account(&cfg, pool, receiver)
+            process_close_account(&cfg, &resolve_stake_pool(&cfg, pool), receiver)
+        }
+        StakingCommand::CreateAccount { pool } => {
+            process_create_account(&cfg, &resolve_stake_pool(&cfg, pool))
         }
-        StakingCommand::CreateAccount { pool } => process_create_account(&cfg, pool),
         StakingCommand::CreatePool {
             seed,
             realm,
             token_mint,
             unbond_period,
-        } => process_create_pool(&cfg, seed.clone(), realm, token_mint, *unbond_period),
-        StakingCommand::DeriveAccount { owner, pool } => process_derive_account(&cfg, pool, owner),
+        } => process_create_pool(
+            &cfg,
+            seed.clone(),
+            realm,
+            &cfg.tokens.resolve(token_mint)?,
+            *unbond_period,
+        ),
+        StakingCommand::DeriveAccount { owner, pool } => {
+            process_derive_account(&cfg, &resolve_stake_pool(&cfg, pool), owner)
+        }
         StakingCommand::DerivePool { seed, show_related } => {
             process_derive_pool(&cfg, seed, *show_related)
         }
@@ -192,20 +226,35 @@ pub fn entry(overrides: &Overrides, program_id: &Pubkey, subcmd: &StakingCommand
             address,
             json,
             pretty,
-        } => process_get_pool(&cfg, address, DisplayOptions::from_args(*json, *pretty)),
+        } => process_get_pool(
+            &cfg,
+            &resolve_stake_pool(&cfg, address),
+            DisplayOptions::from_args(*json, *pretty).with_labels(&cfg.address_labels),
+        ),
         StakingCommand::WithdrawBonded {
             amount,
+            base_units,
             pool,
             receiver,
-        } => process_withdraw_bonded(&cfg, *amount, pool, receiver),
+        } => process_withdraw_bonded(
+            &cfg,
+            amount,
+            *base_units,
+            &resolve_stake_pool(&cfg, pool),
+            receiver,
+        ),
         StakingCommand::WithdrawUnbonded {
             pool,
             rent_receiver,
             token_receiver,
             unbonding_account,
-        } => {
-            process_withdraw_unbonded(&cfg, pool, rent_receiver, token_receiver, unbonding_account)
-        }
+        } => process_withdraw_unbonded(
+            &cfg,
+            &resolve_stake_pool(&cfg, pool),
+            rent_receiver,
+            token_receiver,
+            unbonding_account,
+        ),
     }
 }
 
@@ -218,16 +267,23 @@ fn process_get_account(
     pool: &Pubkey,
     display: DisplayOptions,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
-    let owner_pk = owner.unwrap_or(signer.pubkey());
-    let stake_account = address.unwrap_or(derive_stake_account(pool, &owner_pk, &program.id()));
+    let program = create_read_only_client(cfg);
+    let stake_account = match address {
+        Some(addr) => *addr,
+        None => derive_stake_account(pool, &cfg.owner_or_signer(owner)?, &program.id()),
+    };
     print_serialized(program.account::<StakeAccount>(stake_account)?, &display)
 }
 
 /// The function handler for the staking subcommand that allows users to add
 /// stake to their designated staking account from an owned token account.
-fn process_add_stake(cfg: &Config, amount: &Option<u64>, pool: &Pubkey) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+fn process_add_stake(
+    cfg: &Config,
+    amount: &Option<TokenAmount>,
+    base_units: bool,
+    pool: &Pubkey,
+) -> Result<()> {
+    let (program, signer) = create_program_client(cfg)?;
     let mut req = program.request();
     let mut ix_names = Vec::<&str>::new();
 
@@ -241,6 +297,17 @@ fn process_add_stake(cfg: &Config, amount: &Option<u64>, pool: &Pubkey) -> Resul
         ..
     } = program.account(*pool)?;
 
+    // Resolve the base unit amount to stake from the decimals of the pool's token mint,
+    // leaving it unset if the maximum is requested so the program stakes everything
+    let decimals = mint_decimals(&program, &token_mint)?;
+    let amount = match amount {
+        Some(amt) => {
+            cfg.tokens.verify_symbol(amt, &token_mint)?;
+            amt.to_base_units(decimals, base_units)?
+        }
+        None => None,
+    };
+
     sp.finish_with_message("Stake pool accounts retrieved");
 
     sp = Spinner::new("Preprending required instructions");
@@ -254,11 +321,11 @@ fn process_add_stake(cfg: &Config, amount: &Option<u64>, pool: &Pubkey) -> Resul
             &instruction::InitStakeAccount {},
             accounts::InitStakeAccount {
                 owner: signer.pubkey(),
-                auth: derive_auth_account(&signer.pubkey(), &jet_auth::ID), // TODO: genericize auth program ID (?)
+                auth: derive_auth_account(&signer.pubkey(), &cfg.programs.get(JetProgram::Auth)),
                 stake_pool: *pool,
                 stake_account,
                 voter_weight_record,
-                payer: signer.pubkey(),
+                payer: cfg.fee_payer.pubkey(),
                 system_program,
             }
             .to_account_metas(None),
@@ -269,7 +336,7 @@ fn process_add_stake(cfg: &Config, amount: &Option<u64>, pool: &Pubkey) -> Resul
 
     req = req.instruction(Instruction::new_with_borsh(
         program.id(),
-        &instruction::AddStake { amount: *amount },
+        &instruction::AddStake { amount },
         accounts::AddStake {
             stake_pool: *pool,
             stake_pool_vault,
@@ -283,7 +350,14 @@ fn process_add_stake(cfg: &Config, amount: &Option<u64>, pool: &Pubkey) -> Resul
         .to_account_metas(None),
     ));
 
-    ix_names.push("jet_staking::AddStake");
+    let add_stake_name = match amount {
+        Some(amt) => format!(
+            "jet_staking::AddStake {}",
+            UiAmount::new(amt, decimals).with_symbol(cfg.tokens.symbol(&token_mint))
+        ),
+        None => "jet_staking::AddStake max".to_owned(),
+    };
+    ix_names.push(add_stake_name.as_str());
     sp.finish_with_message("Instruction bytes compiled");
 
     send_with_approval(cfg, req.signer(signer.as_ref()), ix_names)
@@ -291,7 +365,7 @@ fn process_add_stake(cfg: &Config, amount: &Option<u64>, pool: &Pubkey) -> Resul
 
 /// The function handler for a user closing their staking account.
 fn process_close_account(cfg: &Config, pool: &Pubkey, receiver: &Option<Pubkey>) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
 
     // Derive the public key of the `jet_staking::StakeAccount` that
     // is being closed in the instruction call and assert that is exists
@@ -322,7 +396,7 @@ fn process_close_account(cfg: &Config, pool: &Pubkey, receiver: &Option<Pubkey>)
 /// The function handler for the staking subcommand that allows users to create a
 /// new staking account for a designated pool for themselves.
 fn process_create_account(cfg: &Config, pool: &Pubkey) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
 
     // Derive the public keys for the user's `jet_auth::UserAuthentication`
     // and `jet_staking::StakeAccount` program accounts and assert that the
@@ -343,7 +417,7 @@ fn process_create_account(cfg: &Config, pool: &Pubkey) -> Result<()> {
                 auth,
                 stake_pool: *pool,
                 stake_account,
-                payer: signer.pubkey(),
+                payer: cfg.fee_payer.pubkey(),
                 voter_weight_record,
                 system_program,
             })
@@ -366,7 +440,7 @@ fn process_create_pool(
     token_mint: &Pubkey,
     unbond_period: u64,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
 
     // Derive the public keys needed for a new staking pool and
     // ensure that the pool address doesn't already exist
@@ -385,7 +459,7 @@ fn process_create_pool(
         program
             .request()
             .accounts(accounts::InitPool {
-                payer: signer.pubkey(),
+                payer: cfg.fee_payer.pubkey(),
                 authority: signer.pubkey(),
                 token_mint: *token_mint,
                 stake_pool: pool,
@@ -415,7 +489,7 @@ fn process_create_pool(
 /// The function handler to derive the stake account public key for a user
 /// that is associated with a certain stake pool.
 fn process_derive_account(cfg: &Config, pool: &Pubkey, owner: &Option<Pubkey>) -> Result<()> {
-    let acc_owner = owner.unwrap_or(cfg.keypair.pubkey());
+    let acc_owner = cfg.owner_or_signer(owner)?;
     let pk = derive_stake_account(pool, &acc_owner, &cfg.program_id);
     println!("{}", pk);
     Ok(())
@@ -436,7 +510,7 @@ fn process_derive_pool(cfg: &Config, seed: &str, show_related: bool) -> Result<(
 
 /// The function handler to fetch and view the data from a stake pool account.
 fn process_get_pool(cfg: &Config, address: &Pubkey, display: DisplayOptions) -> Result<()> {
-    let (program, _) = create_program_client(cfg);
+    let program = create_read_only_client(cfg);
     print_serialized(program.account::<StakePool>(*address)?, &display)
 }
 
@@ -444,18 +518,27 @@ fn process_get_pool(cfg: &Config, address: &Pubkey, display: DisplayOptions) ->
 /// withdraw bonded stake from the designated pool.
 fn process_withdraw_bonded(
     cfg: &Config,
-    amount: u64,
+    amount: &TokenAmount,
+    base_units: bool,
     pool: &Pubkey,
     receiver: &Option<Pubkey>,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
 
     let token_receiver = receiver.unwrap_or(signer.pubkey());
 
     let StakePool {
-        stake_pool_vault, ..
+        stake_pool_vault,
+        token_mint,
+        ..
     } = program.account(*pool)?;
 
+    cfg.tokens.verify_symbol(amount, &token_mint)?;
+    let decimals = mint_decimals(&program, &token_mint)?;
+    let amount = amount
+        .to_base_units(decimals, base_units)?
+        .ok_or_else(|| anyhow!("`max` is not a supported amount for withdrawing bonded funds"))?;
+
     // Build and send the `jet_staking::instruction::WithdrawBonded` transaction
     send_with_approval(
         cfg,
@@ -470,7 +553,11 @@ fn process_withdraw_bonded(
             })
             .args(instruction::WithdrawBonded { amount })
             .signer(signer.as_ref()),
-        vec!["jet_staking::WithdrawBonded"],
+        vec![format!(
+            "jet_staking::WithdrawBonded {}",
+            UiAmount::new(amount, decimals).with_symbol(cfg.tokens.symbol(&token_mint))
+        )
+        .as_str()],
     )
 }
 
@@ -483,7 +570,7 @@ fn process_withdraw_unbonded(
     token_receiver: &Option<Pubkey>,
     unbonding_account: &Pubkey,
 ) -> Result<()> {
-    let (program, signer) = create_program_client(cfg);
+    let (program, signer) = create_program_client(cfg)?;
 
     let stake_account = derive_stake_account(pool, &signer.pubkey(), &program.id());
 
@@ -15,30 +15,67 @@
 
 use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
 use anchor_client::solana_sdk::pubkey::Pubkey;
+#[cfg(test)]
 use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::solana_sdk::signer::{Signer, SignerError};
 use anchor_client::Cluster;
 use anyhow::{anyhow, Result};
+use clap::builder::FalseyValueParser;
 use clap::{Parser, PossibleValue, ValueHint};
+use serde::{Deserialize, Serialize};
 use solana_cli_config::Config as SolanaConfig;
-use std::fs::read_to_string;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::proposal::proposal_authority;
+use crate::signer::{LazySigner, SignerSource};
+use crate::token::{TokenConfig, TokenRegistry};
+
+/// The default location of the Jet CLI configuration file.
+pub const JET_CONFIG_FILE: &str = "~/.config/jet/config.toml";
+
+/// The keys of the values that can be set in a configuration profile. Program
+/// ID overrides are keyed as `programs.<name>` (e.g. `programs.jet_margin`).
+pub const PROFILE_KEYS: &[&str] = &[
+    "commitment",
+    "keypair",
+    "stake_pool",
+    "url",
+    "websocket_url",
+];
 
 /// The struct definition of the available global command
 /// options that can be used to override or set standard behavior.
 #[derive(Debug, Parser)]
 pub struct Overrides {
+    /// Create a proposal of the SPL Governance account or Squads multisig at the address instead of sending the transaction.
+    #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+    as_proposal: Option<Pubkey>,
     /// Auto-approve the signing and execution of the command transaction(s).
-    #[clap(global = true, long, value_parser)]
+    #[clap(global = true, long, env = "JET_AUTO_APPROVE", value_parser = FalseyValueParser::new())]
     auto_approve: bool,
     /// Override of the commitment level used for the RPC client.
-    #[clap(global = true, long, value_parser = [PossibleValue::new("confirmed"), PossibleValue::new("finalized"), PossibleValue::new("processed")])]
+    #[clap(global = true, long, env = "JET_COMMITMENT", value_parser = [PossibleValue::new("confirmed"), PossibleValue::new("finalized"), PossibleValue::new("processed")])]
     commitment: Option<CommitmentConfig>,
-    /// Override of the path to the keypair to be used as signer.
+    /// Keypair path or signer URI of the account paying transaction fees and rent instead of the signer.
     #[clap(global = true, long, value_parser, value_hint = ValueHint::FilePath)]
+    fee_payer: Option<String>,
+    /// Override of the keypair path or signer URI (`usb://ledger?key=0`, `prompt://`, `stdin://`, `file://<path>` or `exec://<command>`) to be used as signer.
+    #[clap(global = true, long, env = "JET_KEYPAIR", hide_env_values = true, value_parser, value_hint = ValueHint::FilePath)]
     keypair: Option<String>,
+    /// Named profile of the Jet configuration file to use instead of the active profile.
+    #[clap(global = true, long, value_parser)]
+    profile: Option<String>,
+    /// Override of a program ID in the registry as `<name>=<pubkey>` (e.g. `jet_metadata=...`).
+    #[clap(global = true, long = "program-id", value_parser = parse_program_id_override)]
+    program_ids: Vec<(JetProgram, Pubkey)>,
     /// Override of the cluster or RPC URL to use (or their first letter): ["mainnet-beta", "devnet", "testnet", "localnet"].
-    #[clap(global = true, short = 'u', long, value_parser)]
+    #[clap(global = true, short = 'u', long, env = "JET_URL", value_parser)]
     url: Option<Cluster>,
     /// Enables logging verbosity for things like transaction signatures.
     #[clap(global = true, short = 'v', long, value_parser)]
@@ -51,58 +88,436 @@ pub struct Overrides {
 impl Default for Overrides {
     fn default() -> Self {
         Self {
+            as_proposal: None,
             auto_approve: false,
             commitment: Some(CommitmentConfig::confirmed()),
+            fee_payer: None,
             keypair: Some("~/.config/solana/id.json".into()),
+            profile: None,
+            program_ids: Vec::new(),
             url: Some(Cluster::Devnet),
             verbose: false,
         }
     }
 }
 
+impl Overrides {
+    /// The name of the configuration profile selected with `--profile`.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+}
+
+/// The Jet programs that commands interact with and
+/// whose IDs can be overridden by a configuration profile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JetProgram {
+    Auth,
+    Margin,
+    MarginPool,
+    Metadata,
+    Rewards,
+    Staking,
+}
+
+impl JetProgram {
+    /// All of the programs that the command line tool interacts with.
+    pub const ALL: [Self; 6] = [
+        Self::Auth,
+        Self::Margin,
+        Self::MarginPool,
+        Self::Metadata,
+        Self::Rewards,
+        Self::Staking,
+    ];
+
+    /// The program ID compiled into the program's crate.
+    pub fn default_id(&self) -> Pubkey {
+        match self {
+            Self::Auth => jet_auth::ID,
+            Self::Margin => jet_margin::ID,
+            Self::MarginPool => jet_margin_pool::ID,
+            Self::Metadata => jet_metadata::ID,
+            Self::Rewards => jet_rewards::ID,
+            Self::Staking => jet_staking::ID,
+        }
+    }
+
+    /// The name of the environment variable that overrides the program ID (e.g. `JET_MARGIN_PROGRAM_ID`).
+    pub fn env_var(&self) -> String {
+        format!("{}_PROGRAM_ID", self.name().to_uppercase())
+    }
+
+    /// The crate name of the program used to key its ID override.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auth => "jet_auth",
+            Self::Margin => "jet_margin",
+            Self::MarginPool => "jet_margin_pool",
+            Self::Metadata => "jet_metadata",
+            Self::Rewards => "jet_rewards",
+            Self::Staking => "jet_staking",
+        }
+    }
+}
+
+impl fmt::Display for JetProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for JetProgram {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| anyhow!("unknown program name: {}", s))
+    }
+}
+
+/// A named profile of cluster, signer and program settings
+/// in the Jet configuration file. Unset values fall back to
+/// the Solana CLI configuration or the compiled defaults.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keypair: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake_pool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub websocket_url: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub programs: BTreeMap<String, String>,
+}
+
+impl Profile {
+    /// The cluster of the profile's URL, using the websocket URL if one is also set.
+    pub fn cluster(&self) -> Result<Option<Cluster>> {
+        let cluster = match &self.url {
+            Some(url) => Cluster::from_str(url)?,
+            None => return Ok(None),
+        };
+
+        Ok(Some(match &self.websocket_url {
+            Some(ws) => Cluster::Custom(cluster.url().to_owned(), ws.clone()),
+            None => cluster,
+        }))
+    }
+
+    /// The commitment level set in the profile.
+    pub fn commitment(&self) -> Result<Option<CommitmentConfig>> {
+        self.commitment.as_deref().map(parse_commitment).transpose()
+    }
+
+    /// Get the value of the argued profile key if it is set.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        if let Some(name) = key.strip_prefix("programs.") {
+            let program = JetProgram::from_str(name)?;
+            return Ok(self.programs.get(program.name()).cloned());
+        }
+
+        Ok(match key {
+            "commitment" => self.commitment.clone(),
+            "keypair" => self.keypair.clone(),
+            "stake_pool" => self.stake_pool.clone(),
+            "url" => self.url.clone(),
+            "websocket_url" => self.websocket_url.clone(),
+            _ => return Err(unknown_key(key)),
+        })
+    }
+
+    /// Validate and set the value of the argued profile key.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        if let Some(name) = key.strip_prefix("programs.") {
+            let program = JetProgram::from_str(name)?;
+            parse_pubkey(key, value)?;
+            self.programs
+                .insert(program.name().to_owned(), value.to_owned());
+            return Ok(());
+        }
+
+        let field = match key {
+            "commitment" => {
+                parse_commitment(value)?;
+                &mut self.commitment
+            }
+            "keypair" => {
+                parse_keypair_source(value)?;
+                &mut self.keypair
+            }
+            "stake_pool" => {
+                parse_pubkey(key, value)?;
+                &mut self.stake_pool
+            }
+            "url" => {
+                Cluster::from_str(value)?;
+                &mut self.url
+            }
+            "websocket_url" => &mut self.websocket_url,
+            _ => return Err(unknown_key(key)),
+        };
+
+        *field = Some(value.to_owned());
+        Ok(())
+    }
+
+    /// The default stake pool set in the profile.
+    pub fn stake_pool(&self) -> Result<Option<Pubkey>> {
+        self.stake_pool
+            .as_deref()
+            .map(|pool| parse_pubkey("stake_pool", pool))
+            .transpose()
+    }
+}
+
+/// The contents of the Jet configuration file holding the named profiles, the active
+/// profile, the address book labels, and the user defined tokens and program ID
+/// overrides keyed by cluster name.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct JetConfigFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub programs: BTreeMap<String, BTreeMap<String, String>>,
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, BTreeMap<String, TokenConfig>>,
+}
+
+impl JetConfigFile {
+    /// The address book labels keyed by the public key they are assigned to,
+    /// keeping only one of the labels for an address with several labels.
+    pub fn address_labels(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.labels
+            .iter()
+            .map(|(label, addr)| (addr.clone(), label.clone()))
+    }
+
+    /// Find the public key assigned to the argued label in the address book.
+    pub fn labeled_address(&self, label: &str) -> Option<Pubkey> {
+        self.labels.get(label).and_then(|addr| addr.parse().ok())
+    }
+
+    /// Read and deserialize the configuration file at the argued
+    /// path, or return an empty configuration if it does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        toml::from_str(&read_to_string(path)?)
+            .map_err(|e| anyhow!("failed to parse {}: {}", path.display(), e))
+    }
+
+    /// The fully-qualified path of the default configuration file location.
+    pub fn path() -> PathBuf {
+        PathBuf::from(shellexpand::tilde(JET_CONFIG_FILE).to_string())
+    }
+
+    /// Find the profile with the argued name, or the active profile if no name is
+    /// provided. An error is returned if a named profile does not exist.
+    pub fn profile(&self, name: Option<&str>) -> Result<Option<&Profile>> {
+        match name.or(self.active_profile.as_deref()) {
+            Some(n) => self
+                .profiles
+                .get(n)
+                .map(Some)
+                .ok_or_else(|| anyhow!("profile `{}` does not exist in {}", n, JET_CONFIG_FILE)),
+            None => Ok(None),
+        }
+    }
+
+    /// Serialize and write the configuration to the argued path,
+    /// creating any missing parent directories.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// The registry of the Jet program IDs to use for a cluster.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramRegistry(BTreeMap<&'static str, Pubkey>);
+
+impl Default for ProgramRegistry {
+    fn default() -> Self {
+        Self(
+            JetProgram::ALL
+                .into_iter()
+                .map(|p| (p.name(), p.default_id()))
+                .collect(),
+        )
+    }
+}
+
+impl ProgramRegistry {
+    /// Create the registry for the argued cluster from the compiled program IDs, which
+    /// are shared by the mainnet, devnet and local validator deployments, and then the
+    /// overrides in the `[programs.<cluster>]` table of the configuration file.
+    pub fn for_cluster(cluster: &Cluster, file: &JetConfigFile) -> Result<Self> {
+        let mut registry = Self::default();
+        if let Some(overrides) = file.programs.get(&cluster_name(cluster)) {
+            registry.apply(overrides)?;
+        }
+        Ok(registry)
+    }
+
+    /// Apply the program ID overrides set in the `JET_*_PROGRAM_ID` environment variables.
+    pub fn apply_env(&mut self) -> Result<()> {
+        for program in JetProgram::ALL {
+            if let Ok(id) = env::var(program.env_var()) {
+                self.set(program, parse_pubkey(&program.env_var(), &id)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the registered ID of the argued program.
+    pub fn get(&self, program: JetProgram) -> Pubkey {
+        self.0[program.name()]
+    }
+
+    /// Override the registered ID of the argued program.
+    pub fn set(&mut self, program: JetProgram, id: Pubkey) {
+        self.0.insert(program.name(), id);
+    }
+
+    /// Apply the program ID overrides of a configuration file table keyed by program name.
+    pub fn apply(&mut self, overrides: &BTreeMap<String, String>) -> Result<()> {
+        for (name, id) in overrides {
+            self.set(
+                JetProgram::from_str(name)?,
+                parse_pubkey(&format!("programs.{}", name), id)?,
+            );
+        }
+        Ok(())
+    }
+}
+
 /// The struct definitions of the options that are transformed
 /// by the global CLI overrides for all commands.
 #[derive(Debug)]
 pub struct Config {
+    pub address_labels: HashMap<String, String>,
+    pub as_proposal: Option<Pubkey>,
     pub auto_approved: bool,
     pub cluster: Cluster,
-    pub keypair: Rc<Keypair>,
+    pub commitment: CommitmentConfig,
+    pub fee_payer: Rc<dyn Signer>,
+    pub keypair: Rc<dyn Signer>,
+    pub labels: BTreeMap<String, String>,
     pub program_id: Pubkey,
+    pub programs: ProgramRegistry,
+    pub proposal_authority: Option<Pubkey>,
+    pub stake_pool: Option<Pubkey>,
+    pub tokens: TokenRegistry,
     pub verbose: bool,
 }
 
 impl Config {
     /// Create a new `Config` instance that processes appropriate file-based configurations
     /// and then applies any argument provided overrides discovered from the command.
-    pub fn new(overrides: &Overrides, program_id: Pubkey) -> Result<Self> {
-        let sol_cfg: SolanaConfig =
-            SolanaConfig::load(solana_cli_config::CONFIG_FILE.as_ref().unwrap())?;
+    ///
+    /// Values are resolved from the command overrides or their `JET_*` environment variables
+    /// first, then the selected profile of the Jet configuration file and lastly the Solana
+    /// CLI configuration, if one exists. The address labels of the Solana CLI configuration
+    /// are merged with, and overridden by, the Jet address book. Program IDs are registered for the resolved cluster
+    /// and then overridden by the configuration file's table for the cluster, the profile,
+    /// the `JET_*_PROGRAM_ID` variables, `--program-id` and lastly `program_id`. The authority
+    /// of an `--as-proposal` target is resolved here, since instructions are built for it.
+    pub fn new(
+        overrides: &Overrides,
+        program: JetProgram,
+        program_id: Option<Pubkey>,
+    ) -> Result<Self> {
+        let sol_cfg = load_solana_config()?;
 
-        let n_keypair = normalize_path_arg(
+        let jet_cfg = JetConfigFile::load(&JetConfigFile::path())?;
+        let profile = jet_cfg
+            .profile(overrides.profile())?
+            .cloned()
+            .unwrap_or_default();
+
+        let keypair: Rc<dyn Signer> = Rc::new(LazySigner::new(
+            overrides
+                .keypair
+                .as_ref()
+                .or(profile.keypair.as_ref())
+                .unwrap_or(&sol_cfg.keypair_path),
             "--keypair",
-            overrides.keypair.as_ref().unwrap_or(&sol_cfg.keypair_path),
-        )?;
-
-        let keypair = {
-            let data = read_to_string(&n_keypair)?;
-            let bytes: Vec<u8> = serde_json::from_str(&data)?;
-            Keypair::from_bytes(&bytes)
-        }?;
-
-        let cluster = overrides
-            .url
-            .as_ref()
-            .unwrap_or(&Cluster::Custom(
-                sol_cfg.json_rpc_url,
-                sol_cfg.websocket_url,
-            ))
-            .to_owned();
+        ));
+
+        let fee_payer: Rc<dyn Signer> = match &overrides.fee_payer {
+            Some(uri) => Rc::new(LazySigner::new(uri, "--fee-payer")),
+            None => keypair.clone(),
+        };
+
+        let cluster = match (&overrides.url, profile.cluster()?) {
+            (Some(url), _) => url.clone(),
+            (None, Some(c)) => c,
+            (None, None) => {
+                let ws = if sol_cfg.websocket_url.is_empty() {
+                    SolanaConfig::compute_websocket_url(&sol_cfg.json_rpc_url)
+                } else {
+                    sol_cfg.websocket_url
+                };
+                Cluster::Custom(sol_cfg.json_rpc_url, ws)
+            }
+        };
+
+        let commitment = match (overrides.commitment, profile.commitment()?) {
+            (Some(c), _) | (None, Some(c)) => c,
+            (None, None) => parse_commitment(&sol_cfg.commitment)?,
+        };
+
+        let mut address_labels = sol_cfg.address_labels;
+        address_labels.extend(jet_cfg.address_labels());
+
+        let mut programs = ProgramRegistry::for_cluster(&cluster, &jet_cfg)?;
+        programs.apply(&profile.programs)?;
+        programs.apply_env()?;
+        for (p, id) in &overrides.program_ids {
+            programs.set(*p, *id);
+        }
+        if let Some(id) = program_id {
+            programs.set(program, id);
+        }
+
+        let proposal_authority = overrides
+            .as_proposal
+            .map(|target| proposal_authority(&cluster, commitment, &target))
+            .transpose()?;
+
+        let tokens = TokenRegistry::for_cluster(&cluster, &jet_cfg)?;
 
         Ok(Self {
+            address_labels,
+            as_proposal: overrides.as_proposal,
             auto_approved: overrides.auto_approve,
             cluster,
-            keypair: Rc::new(keypair),
-            program_id,
+            commitment,
+            fee_payer,
+            keypair,
+            labels: jet_cfg.labels.clone(),
+            program_id: programs.get(program),
+            programs,
+            proposal_authority,
+            stake_pool: profile.stake_pool()?,
+            tokens,
             verbose: overrides.verbose,
         })
     }
@@ -110,13 +525,50 @@ impl Config {
     /// Create a new instance of `Config` from another with a different program ID.
     pub fn clone_with_program(&self, program_id: Pubkey) -> Self {
         Self {
+            address_labels: self.address_labels.clone(),
+            as_proposal: self.as_proposal,
             auto_approved: self.auto_approved,
             cluster: self.cluster.clone(),
+            commitment: self.commitment,
+            fee_payer: self.fee_payer.clone(),
             keypair: self.keypair.clone(),
+            labels: self.labels.clone(),
             program_id,
+            programs: self.programs.clone(),
+            proposal_authority: self.proposal_authority,
+            stake_pool: self.stake_pool,
+            tokens: self.tokens.clone(),
             verbose: self.verbose,
         }
     }
+
+    /// Get the argued owner or default to the proposal authority when `--as-proposal` was
+    /// provided, otherwise to the public key of the configured signer, returning an error
+    /// that asks for `--owner` if no signer could be loaded.
+    pub fn owner_or_signer(&self, owner: &Option<Pubkey>) -> Result<Pubkey> {
+        match owner.or(self.proposal_authority) {
+            Some(o) => Ok(o),
+            None => self.signer_pubkey().map_err(|e| {
+                anyhow!(
+                    "an owner is required through `--owner` when no signer is configured\n{}",
+                    e
+                )
+            }),
+        }
+    }
+
+    /// Load the configured signer and fee payer if they have not been loaded
+    /// yet, returning an error if either of them is unavailable.
+    pub fn require_signers(&self) -> Result<()> {
+        self.signer_pubkey()?;
+        self.fee_payer.try_pubkey().map_err(signer_error)?;
+        Ok(())
+    }
+
+    /// Get the public key of the configured signer, loading it if needed.
+    pub fn signer_pubkey(&self) -> Result<Pubkey> {
+        self.keypair.try_pubkey().map_err(signer_error)
+    }
 }
 
 /// Default implementation for the `Config` struct purposed for
@@ -124,18 +576,125 @@ impl Config {
 #[cfg(test)]
 impl Default for Config {
     fn default() -> Self {
+        let keypair: Rc<dyn Signer> = Rc::new(Keypair::new());
         Self {
+            address_labels: HashMap::default(),
+            as_proposal: None,
             auto_approved: bool::default(),
             cluster: Cluster::default(),
-            keypair: Rc::new(Keypair::new()),
+            commitment: CommitmentConfig::confirmed(),
+            fee_payer: keypair.clone(),
+            keypair,
+            labels: BTreeMap::default(),
             program_id: Pubkey::default(),
+            programs: ProgramRegistry::default(),
+            proposal_authority: None,
+            stake_pool: None,
+            tokens: TokenRegistry::default(),
             verbose: bool::default(),
         }
     }
 }
 
+/// The name of the argued cluster used to key its program ID overrides and tokens, which
+/// is the cluster's moniker (e.g. `devnet`) for well-known RPC URLs or otherwise its URL.
+pub fn cluster_name(cluster: &Cluster) -> String {
+    [
+        Cluster::Mainnet,
+        Cluster::Devnet,
+        Cluster::Testnet,
+        Cluster::Localnet,
+    ]
+    .into_iter()
+    .find(|c| c.url() == cluster.url())
+    .unwrap_or_else(|| cluster.clone())
+    .to_string()
+}
+
+/// Find the public key assigned to the argued label in the map of labels keyed by public key.
+fn find_label(labels: &HashMap<String, String>, label: &str) -> Option<Pubkey> {
+    labels
+        .iter()
+        .find(|(_, l)| l.as_str() == label)
+        .and_then(|(addr, _)| addr.parse().ok())
+}
+
+/// Load the Solana CLI configuration file, or its default values if it does not exist.
+pub fn load_solana_config() -> Result<SolanaConfig> {
+    match solana_cli_config::CONFIG_FILE.as_ref() {
+        Some(path) if Path::new(path).exists() => Ok(SolanaConfig::load(path)?),
+        _ => Ok(SolanaConfig::default()),
+    }
+}
+
+/// Parse a public key argument that is either base-58 encoded or a label of the
+/// Jet address book or the Solana CLI configuration's `address_labels`.
+pub fn parse_address(value: &str) -> Result<Pubkey> {
+    if let Ok(pk) = Pubkey::from_str(value) {
+        return Ok(pk);
+    }
+
+    // Address book labels are resolved first so that an address with several
+    // labels, or with a label in both configurations, is found by each label
+    let labeled = match JetConfigFile::load(&JetConfigFile::path())?.labeled_address(value) {
+        Some(pk) => Some(pk),
+        None => find_label(&load_solana_config()?.address_labels, value),
+    };
+
+    labeled.ok_or_else(|| anyhow!("`{}` is not a public key or known address label", value))
+}
+
+/// Parse the argued commitment level name into its config.
+fn parse_commitment(value: &str) -> Result<CommitmentConfig> {
+    CommitmentConfig::from_str(value).map_err(|_| anyhow!("invalid commitment level: {}", value))
+}
+
+/// Parse the argued base-58 public key value of a profile key.
+fn parse_pubkey(key: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| anyhow!("invalid public key for `{}`: {}", key, value))
+}
+
+/// Parse the argued signer of the `keypair` profile key, rejecting inline keypairs so
+/// that a secret key is never stored in, or displayed from, the configuration file.
+fn parse_keypair_source(value: &str) -> Result<SignerSource> {
+    match SignerSource::from_str(value)? {
+        SignerSource::Inline(_) => Err(anyhow!(
+            "inline keypairs cannot be stored in a profile, provide a keypair file path or signer URI"
+        )),
+        source => Ok(source),
+    }
+}
+
+/// Parse a `--program-id` override of the form `<name>=<pubkey>`.
+fn parse_program_id_override(value: &str) -> Result<(JetProgram, Pubkey)> {
+    let (name, id) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("program ID overrides must be `<name>=<pubkey>`: {}", value))?;
+    Ok((JetProgram::from_str(name)?, parse_pubkey(name, id)?))
+}
+
+/// Convert the error of a signer that failed to load into a descriptive error.
+fn signer_error(e: SignerError) -> anyhow::Error {
+    match e {
+        SignerError::Custom(msg) => anyhow!(
+            "{}\nprovide a signer with `--keypair`, `JET_KEYPAIR` or a configuration profile",
+            msg
+        ),
+        e => anyhow!(e),
+    }
+}
+
+/// Create the error for an unrecognized profile key.
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow!(
+        "unknown profile key `{}`, expected one of {:?} or `programs.<name>`",
+        key,
+        PROFILE_KEYS
+    )
+}
+
 /// Normalizes the argued filepath based string into a fully-qualified system path.
-fn normalize_path_arg(name: &str, val: &str) -> Result<PathBuf> {
+pub(crate) fn normalize_path_arg(name: &str, val: &str) -> Result<PathBuf> {
     let normalized = if val.starts_with('~') {
         PathBuf::from(shellexpand::tilde(&val).to_string())
     } else {
@@ -174,20 +733,243 @@ mod tests {
 
     #[test]
     fn cfg_clone_with_new_program() {
-        let cfg = Config::new(&Overrides::default(), Pubkey::default()).unwrap();
+        let cfg = Config::new(
+            &Overrides::default(),
+            JetProgram::Staking,
+            Some(Pubkey::default()),
+        )
+        .unwrap();
         let new_cfg = cfg.clone_with_program(Pubkey::new_from_array([5; 32]));
         assert_ne!(new_cfg.program_id, Pubkey::default());
     }
 
+    #[test]
+    fn jet_config_file_profiles() {
+        let file: JetConfigFile = toml::from_str(
+            r#"
+            active_profile = "devnet"
+
+            [profiles.devnet]
+            url = "devnet"
+            commitment = "finalized"
+            stake_pool = "11111111111111111111111111111111"
+
+            [profiles.devnet.programs]
+            jet_margin = "11111111111111111111111111111111"
+
+            [profiles.local]
+            url = "http://127.0.0.1:8899"
+            websocket_url = "ws://127.0.0.1:8900"
+            "#,
+        )
+        .unwrap();
+
+        let devnet = file.profile(None).unwrap().unwrap();
+        assert_eq!(devnet.cluster().unwrap(), Some(Cluster::Devnet));
+        assert_eq!(
+            devnet.commitment().unwrap(),
+            Some(CommitmentConfig::finalized())
+        );
+        assert_eq!(devnet.stake_pool().unwrap(), Some(Pubkey::default()));
+
+        let local = file.profile(Some("local")).unwrap().unwrap();
+        assert_eq!(
+            local.cluster().unwrap(),
+            Some(Cluster::Custom(
+                "http://127.0.0.1:8899".into(),
+                "ws://127.0.0.1:8900".into()
+            ))
+        );
+
+        assert!(file.profile(Some("mainnet")).is_err());
+        assert_eq!(JetConfigFile::default().profile(None).unwrap(), None);
+
+        let round_trip: JetConfigFile = toml::from_str(&toml::to_string(&file).unwrap()).unwrap();
+        assert_eq!(round_trip, file);
+    }
+
+    #[test]
+    fn jet_config_file_address_labels() {
+        let file: JetConfigFile = toml::from_str(
+            r#"
+            [labels]
+            usdc-pool = "11111111111111111111111111111111"
+            system = "11111111111111111111111111111111"
+            "#,
+        )
+        .unwrap();
+
+        let labels: HashMap<String, String> = file.address_labels().collect();
+        assert_eq!(find_label(&labels, "usdc-pool"), Some(Pubkey::default()));
+        assert_eq!(find_label(&labels, "sol-pool"), None);
+        assert_eq!(file.labeled_address("usdc-pool"), Some(Pubkey::default()));
+        assert_eq!(file.labeled_address("system"), Some(Pubkey::default()));
+        assert_eq!(file.labeled_address("sol-pool"), None);
+
+        let round_trip: JetConfigFile = toml::from_str(&toml::to_string(&file).unwrap()).unwrap();
+        assert_eq!(round_trip, file);
+        assert_eq!(
+            parse_address("11111111111111111111111111111111").unwrap(),
+            Pubkey::default()
+        );
+    }
+
+    #[test]
+    fn program_registry_overrides() {
+        let file: JetConfigFile = toml::from_str(
+            r#"
+            [programs.localnet]
+            jet_metadata = "11111111111111111111111111111111"
+            "#,
+        )
+        .unwrap();
+
+        let local = ProgramRegistry::for_cluster(&Cluster::Localnet, &file).unwrap();
+        assert_eq!(local.get(JetProgram::Metadata), Pubkey::default());
+
+        let mut registry = ProgramRegistry::for_cluster(&Cluster::Devnet, &file).unwrap();
+        assert_eq!(registry.get(JetProgram::Margin), jet_margin::ID);
+        assert_eq!(registry.get(JetProgram::Metadata), jet_metadata::ID);
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert(
+            "jet_margin".to_owned(),
+            "11111111111111111111111111111111".to_owned(),
+        );
+        registry.apply(&overrides).unwrap();
+        assert_eq!(registry.get(JetProgram::Margin), Pubkey::default());
+        assert_eq!(registry.get(JetProgram::Staking), jet_staking::ID);
+
+        overrides.insert(
+            "jet_unknown".to_owned(),
+            "11111111111111111111111111111111".to_owned(),
+        );
+        assert!(registry.apply(&overrides).is_err());
+
+        assert_eq!(
+            parse_program_id_override("jet_staking=11111111111111111111111111111111").unwrap(),
+            (JetProgram::Staking, Pubkey::default())
+        );
+        assert!(parse_program_id_override("jet_staking").is_err());
+        assert!(parse_program_id_override("jet_staking=bad").is_err());
+    }
+
+    #[test]
+    fn cluster_names_for_program_overrides() {
+        assert_eq!(cluster_name(&Cluster::Devnet), "devnet");
+        assert_eq!(
+            cluster_name(&Cluster::Custom(
+                "https://api.devnet.solana.com".into(),
+                "wss://api.devnet.solana.com".into()
+            )),
+            "devnet"
+        );
+        assert_eq!(
+            cluster_name(&Cluster::Custom(
+                "https://rpc.example.com".into(),
+                "wss://rpc.example.com".into()
+            )),
+            "https://rpc.example.com"
+        );
+    }
+
+    #[test]
+    fn program_registry_env_overrides() {
+        assert_eq!(
+            JetProgram::MarginPool.env_var(),
+            "JET_MARGIN_POOL_PROGRAM_ID"
+        );
+
+        env::set_var(
+            JetProgram::Rewards.env_var(),
+            "11111111111111111111111111111111",
+        );
+        let mut registry = ProgramRegistry::default();
+        registry.apply_env().unwrap();
+        env::remove_var(JetProgram::Rewards.env_var());
+
+        assert_eq!(registry.get(JetProgram::Rewards), Pubkey::default());
+        assert_eq!(registry.get(JetProgram::Auth), jet_auth::ID);
+    }
+
+    #[test]
+    fn profile_get_and_set_keys() {
+        let mut profile = Profile::default();
+        profile.set("url", "mainnet-beta").unwrap();
+        profile
+            .set("programs.jet_staking", "11111111111111111111111111111111")
+            .unwrap();
+
+        assert_eq!(profile.get("url").unwrap(), Some("mainnet-beta".into()));
+        assert_eq!(profile.get("keypair").unwrap(), None);
+        assert_eq!(
+            profile.get("programs.jet_staking").unwrap(),
+            Some("11111111111111111111111111111111".into())
+        );
+
+        assert!(profile.set("commitment", "eventually").is_err());
+        assert!(profile
+            .set("keypair", &Keypair::new().to_base58_string())
+            .is_err());
+        assert!(profile.set("keypair", "[1, 2, 3]").is_err());
+        assert_eq!(profile.get("keypair").unwrap(), None);
+        profile.set("keypair", "usb://ledger?key=0").unwrap();
+        assert!(profile.set("stake_pool", "not-a-pubkey").is_err());
+        assert!(profile
+            .set("programs.jet_unknown", "11111111111111111111111111111111")
+            .is_err());
+        assert!(profile.get("unknown").is_err());
+    }
+
     #[test]
     fn cfg_persists_cluster() {
-        let cfg = Config::new(&Overrides::default(), Pubkey::default()).unwrap();
+        let cfg = Config::new(
+            &Overrides::default(),
+            JetProgram::Staking,
+            Some(Pubkey::default()),
+        )
+        .unwrap();
         assert_eq!(cfg.cluster, Cluster::Devnet);
     }
 
+    #[test]
+    fn cfg_loads_signer_lazily() {
+        let overrides = Overrides {
+            keypair: Some("/tmp/jet-missing-keypair.json".into()),
+            ..Default::default()
+        };
+        let cfg = Config::new(&overrides, JetProgram::Staking, Some(Pubkey::default())).unwrap();
+        assert!(cfg.signer_pubkey().is_err());
+        assert!(cfg.require_signers().is_err());
+
+        let owner = Pubkey::new_unique();
+        assert_eq!(cfg.owner_or_signer(&Some(owner)).unwrap(), owner);
+        assert!(cfg
+            .owner_or_signer(&None)
+            .unwrap_err()
+            .to_string()
+            .contains("--owner"));
+    }
+
+    #[test]
+    fn cfg_defaults_owner_to_proposal_authority() {
+        let authority = Pubkey::new_unique();
+        let cfg = Config {
+            proposal_authority: Some(authority),
+            ..Default::default()
+        };
+        assert_eq!(cfg.owner_or_signer(&None).unwrap(), authority);
+        assert_ne!(cfg.signer_pubkey().unwrap(), authority);
+    }
+
     #[test]
     fn cfg_read_keypair_bytes() {
-        let cfg = Config::new(&Overrides::default(), Pubkey::default()).unwrap();
-        assert!(cfg.keypair.to_base58_string().len() >= 32);
+        let cfg = Config::new(
+            &Overrides::default(),
+            JetProgram::Staking,
+            Some(Pubkey::default()),
+        )
+        .unwrap();
+        assert_ne!(cfg.keypair.pubkey(), Pubkey::default());
     }
 }
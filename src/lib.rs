@@ -15,17 +15,23 @@
 
 use anchor_client::solana_sdk::pubkey::Pubkey;
 use anyhow::Result;
-use clap::{AppSettings, Parser};
+use clap::{AppSettings, Parser, ValueHint};
+use clap_complete::Shell;
+use std::path::PathBuf;
 
+mod amount;
 mod cmd;
 mod config;
 mod macros;
 mod program;
+mod proposal;
 mod pubkey;
+mod signer;
 mod terminal;
+mod token;
 
 use cmd::*;
-use config::Overrides;
+use config::{parse_address, Overrides};
 
 /// Jet Protocol command line interface for interacting
 /// with the various programs.
@@ -48,40 +54,73 @@ enum Command {
     /// jet_rewards program commands for airdrops.
     Airdrop {
         /// Override of the `jet_rewards` program ID.
-        #[clap(global = true, long, value_parser, default_value_t = jet_rewards::ID)]
-        program: Pubkey,
+        #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+        program: Option<Pubkey>,
         #[clap(subcommand)]
         subcmd: airdrop::AirdropCommand,
     },
     /// jet_auth program commands.
     Auth {
         /// Override of the `jet_auth` program ID.
-        #[clap(global = true, long, value_parser, default_value_t = jet_auth::ID)]
-        program: Pubkey,
+        #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+        program: Option<Pubkey>,
         #[clap(subcommand)]
         subcmd: auth::AuthCommand,
     },
+    /// Generate shell completion scripts for the command line tool.
+    Completions {
+        /// The shell to generate the completion script for.
+        #[clap(value_parser, required_unless_present = "addresses")]
+        shell: Option<Shell>,
+        /// List the address candidates of public key arguments for dynamic completions.
+        #[clap(long, hide = true, value_parser)]
+        addresses: bool,
+    },
+    /// Jet configuration file profile commands.
+    Config {
+        #[clap(subcommand)]
+        subcmd: cmd::config::ConfigCommand,
+    },
+    /// Address book commands for labeling public keys.
+    Labels {
+        #[clap(subcommand)]
+        subcmd: labels::LabelsCommand,
+    },
+    /// Generate the roff man pages of the command line tool.
+    Man {
+        /// Write a man page for every command into the directory instead of printing the top-level page.
+        #[clap(long, value_parser, value_hint = ValueHint::DirPath)]
+        out_dir: Option<PathBuf>,
+    },
     /// jet_margin program commands.
     Margin {
         /// Override of the `jet_margin` program ID.
-        #[clap(global = true, long, value_parser, default_value_t = jet_margin::ID)]
-        program: Pubkey,
+        #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+        program: Option<Pubkey>,
         #[clap(subcommand)]
         subcmd: margin::MarginCommand,
     },
     /// jet_margin_pool program commands.
     MarginPool {
         /// Override of the `jet_margin_pool` program ID.
-        #[clap(global = true, long, value_parser, default_value_t = jet_margin_pool::ID)]
-        program: Pubkey,
+        #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+        program: Option<Pubkey>,
         #[clap(subcommand)]
         subcmd: margin_pool::MarginPoolCommand,
     },
+    /// jet_metadata program commands.
+    Metadata {
+        /// Override of the `jet_metadata` program ID.
+        #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+        program: Option<Pubkey>,
+        #[clap(subcommand)]
+        subcmd: metadata::MetadataCommand,
+    },
     /// jet_staking program commands.
     Staking {
         /// Override of the `jet_staking` program ID.
-        #[clap(global = true, long, value_parser, default_value_t = jet_staking::ID)]
-        program: Pubkey,
+        #[clap(global = true, long, value_parser = parse_address, value_name = "PUBKEY")]
+        program: Option<Pubkey>,
         #[clap(subcommand)]
         subcmd: staking::StakingCommand,
     },
@@ -93,8 +132,15 @@ pub fn run(opts: Opts) -> Result<()> {
     match opts.command {
         Command::Airdrop { program, subcmd } => airdrop::entry(&opts.cfg, &program, &subcmd),
         Command::Auth { program, subcmd } => auth::entry(&opts.cfg, &program, &subcmd),
+        Command::Completions { shell, addresses } => {
+            completions::entry(&opts.cfg, &shell, addresses)
+        }
+        Command::Config { subcmd } => cmd::config::entry(&opts.cfg, &subcmd),
+        Command::Labels { subcmd } => labels::entry(&subcmd),
+        Command::Man { out_dir } => man::entry(&out_dir),
         Command::Margin { program, subcmd } => margin::entry(&opts.cfg, &program, &subcmd),
         Command::MarginPool { program, subcmd } => margin_pool::entry(&opts.cfg, &program, &subcmd),
+        Command::Metadata { program, subcmd } => metadata::entry(&opts.cfg, &program, &subcmd),
         Command::Staking { program, subcmd } => staking::entry(&opts.cfg, &program, &subcmd),
     }
 }
@@ -13,14 +13,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::instruction::Instruction;
 use anchor_client::solana_sdk::pubkey::Pubkey;
 use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::solana_sdk::signer::null_signer::NullSigner;
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_client::solana_sdk::transaction::Transaction;
 use anchor_client::{Client, Program, RequestBuilder};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::rc::Rc;
 
 use super::config::Config;
+use super::proposal::wrap_as_proposal;
 use super::terminal::{request_approval, Spinner};
 
 /// Checks whether the account for the argued public key exists.
@@ -30,28 +36,135 @@ pub fn account_exists(program: &Program, public_key: &Pubkey) -> Result<bool> {
     Ok(info.value.is_some())
 }
 
+/// Build a program instruction from the Anchor generated accounts and
+/// instruction data structs, including the instruction discriminator.
+pub fn build_instruction(
+    program_id: &Pubkey,
+    accounts: impl ToAccountMetas,
+    args: impl InstructionData,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
 /// Handle the instantiation of a program client and the
-/// designating signer keypair for the argued config and program ID.
-pub fn create_program_client(config: &Config) -> (Program, Rc<Keypair>) {
-    (
-        Client::new_with_options(
-            config.cluster.clone(),
-            config.keypair.clone(),
-            CommitmentConfig::confirmed(),
-        )
-        .program(config.program_id),
-        config.keypair.clone(),
+/// designating signer for the argued config and program ID.
+///
+/// Transactions are signed by the configured signer in `send_with_approval`
+/// rather than by the client, so the client is given an ephemeral payer to
+/// allow signers that are not a `Keypair`, such as hardware wallets.
+///
+/// The signer and fee payer are loaded here, returning an error if either is
+/// unavailable, so commands that only read or derive accounts should instead
+/// use `create_read_only_client` to run without a configured signer.
+///
+/// With `--as-proposal`, the designated signer is a placeholder for the proposal
+/// authority, so that the instructions and the accounts derived for the signer
+/// are built for the authority that executes them.
+pub fn create_program_client(config: &Config) -> Result<(Program, Rc<dyn Signer>)> {
+    config.require_signers()?;
+
+    let signer: Rc<dyn Signer> = match config.proposal_authority {
+        Some(authority) => Rc::new(NullSigner::new(&authority)),
+        None => config.keypair.clone(),
+    };
+
+    Ok((create_read_only_client(config), signer))
+}
+
+/// Handle the instantiation of a program client for the argued
+/// config and program ID without loading the configured signer.
+pub fn create_read_only_client(config: &Config) -> Program {
+    Client::new_with_options(
+        config.cluster.clone(),
+        Rc::new(Keypair::new()),
+        config.commitment,
     )
+    .program(config.program_id)
 }
 
 /// Wrap a sendable transaction expression to be
 /// sent, confirmed and log the signature hash based on the
 /// detected verbosity setting in the exposed configuration.
+///
+/// If `--as-proposal` was provided, the instructions are instead wrapped into
+/// a governance or multisig proposal that is created by the configured signer.
 pub fn send_with_approval(config: &Config, req: RequestBuilder, ix_names: Vec<&str>) -> Result<()> {
+    let ixs = req.instructions()?;
+
+    if let Some(target) = &config.as_proposal {
+        let proposal = wrap_as_proposal(config, target, ixs, &ix_names)?;
+        request_approval(
+            config,
+            Some(proposal.names.iter().map(String::as_str).collect()),
+        )?;
+        send_instructions(config, &proposal.instructions)?;
+        println!("Proposal: {}", proposal.address);
+        return Ok(());
+    }
+
     request_approval(config, Some(ix_names))?;
+    send_instructions(config, &ixs)
+}
+
+/// Request a single approval of the instructions of all argued batches and
+/// then send each batch as a separate transaction, in order.
+///
+/// Only a single batch can be wrapped with `--as-proposal`, since every
+/// transaction would otherwise create a separate proposal.
+pub fn send_batches_with_approval(
+    config: &Config,
+    mut batches: Vec<(RequestBuilder, Vec<&str>)>,
+) -> Result<()> {
+    if config.as_proposal.is_some() {
+        if batches.len() != 1 {
+            return Err(anyhow!(
+                "`--as-proposal` does not support {} transactions, which would each create a proposal",
+                batches.len()
+            ));
+        }
+        let (req, ix_names) = batches.remove(0);
+        return send_with_approval(config, req, ix_names);
+    }
+
+    let count = batches.len();
+    let mut txs = Vec::with_capacity(count);
+    let mut names = Vec::new();
+
+    for (i, (req, ix_names)) in batches.into_iter().enumerate() {
+        txs.push(req.instructions()?);
+        names.extend(
+            ix_names
+                .iter()
+                .map(|name| format!("{} (transaction {} of {})", name, i + 1, count)),
+        );
+    }
+
+    request_approval(config, Some(names.iter().map(String::as_str).collect()))?;
+
+    for ixs in &txs {
+        send_instructions(config, ixs)?;
+    }
+
+    Ok(())
+}
+
+/// Sign and send a transaction of the argued instructions with the
+/// configured signers and confirm it with a progress spinner.
+fn send_instructions(config: &Config, ixs: &[Instruction]) -> Result<()> {
+    let rpc = RpcClient::new_with_commitment(config.cluster.url().to_owned(), config.commitment);
+
+    // Signing fails rather than panics when a hardware wallet prompt is
+    // rejected or an external signer exits unsuccessfully
+    let mut tx = Transaction::new_with_payer(ixs, Some(&config.fee_payer.pubkey()));
+    tx.try_sign(&transaction_signers(config), rpc.get_latest_blockhash()?)
+        .map_err(|e| anyhow!("failed to sign the transaction: {}", e))?;
 
     let sp = Spinner::new("Sending transaction");
-    let sig = req.send()?;
+    let sig = rpc.send_and_confirm_transaction(&tx)?;
     sp.finish_with_message("Transaction confirmed!");
 
     if config.verbose {
@@ -61,9 +174,18 @@ pub fn send_with_approval(config: &Config, req: RequestBuilder, ix_names: Vec<&s
     Ok(())
 }
 
+/// The unique signers of a transaction for the argued config, being the fee
+/// payer followed by the signer when a separate fee payer was provided.
+fn transaction_signers(config: &Config) -> Vec<&dyn Signer> {
+    let mut signers = vec![config.fee_payer.as_ref()];
+    if config.keypair.pubkey() != config.fee_payer.pubkey() {
+        signers.push(config.keypair.as_ref());
+    }
+    signers
+}
+
 #[cfg(test)]
 mod tests {
-    use anchor_client::solana_sdk::signer::Signer;
     use anchor_client::solana_sdk::system_program;
     use anchor_client::Cluster;
 
@@ -74,20 +196,35 @@ mod tests {
     fn program_client_creates_instance() {
         let config = Config::default();
         let signer_pubkey = config.keypair.pubkey();
-        let p = create_program_client(&config);
+        let p = create_program_client(&config).unwrap();
 
         assert_eq!(p.0.id(), Pubkey::default());
-        assert_eq!(p.0.payer(), signer_pubkey);
         assert_eq!(p.1.pubkey(), signer_pubkey);
     }
 
+    #[test]
+    fn transaction_signers_include_fee_payer() {
+        let config = Config::default();
+        assert_eq!(transaction_signers(&config).len(), 1);
+
+        let fee_payer: Rc<dyn Signer> = Rc::new(Keypair::new());
+        let config = Config {
+            fee_payer: fee_payer.clone(),
+            ..Default::default()
+        };
+        let signers = transaction_signers(&config);
+        assert_eq!(signers.len(), 2);
+        assert_eq!(signers[0].pubkey(), fee_payer.pubkey());
+        assert_eq!(signers[1].pubkey(), config.keypair.pubkey());
+    }
+
     #[test]
     fn account_exists_finds_real_pubkey() {
         let cfg = Config {
             cluster: Cluster::Mainnet,
             ..Default::default()
         };
-        let (program, _) = create_program_client(&cfg);
+        let program = create_read_only_client(&cfg);
         assert!(account_exists(&program, &system_program::ID).unwrap_or(false));
     }
 
@@ -97,7 +234,7 @@ mod tests {
             cluster: Cluster::Mainnet,
             ..Default::default()
         };
-        let (program, _) = create_program_client(&cfg);
+        let program = create_read_only_client(&cfg);
         assert!(account_exists(&program, &Pubkey::default()).unwrap_or(false));
     }
 }
@@ -0,0 +1,422 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::borsh::try_from_slice_unchecked;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::hash::hash;
+use anchor_client::solana_sdk::instruction::{AccountMeta, Instruction};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_client::solana_sdk::system_program::ID as system_program;
+use anchor_client::Cluster;
+use anyhow::{anyhow, Result};
+use spl_governance::instruction::{create_proposal, insert_transaction, sign_off_proposal};
+use spl_governance::state::governance::GovernanceV2;
+use spl_governance::state::proposal::{get_proposal_address, VoteType};
+use spl_governance::state::proposal_transaction::InstructionData;
+use spl_governance::state::realm::RealmV2;
+use spl_governance::state::token_owner_record::get_token_owner_record_address;
+use std::str::FromStr;
+
+use crate::config::Config;
+
+/// The program ID of the Squads v3 multisig program.
+pub const SQUADS_MPL_ID: &str = "SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu";
+
+/// The index of the default Squads vault authority that executes multisig transactions.
+const SQUADS_VAULT_AUTHORITY_INDEX: u32 = 1;
+
+/// The instructions that create a proposal for a set of wrapped
+/// instructions and the address of the proposal to be voted on.
+pub struct ProposalTransaction {
+    pub address: Pubkey,
+    pub instructions: Vec<Instruction>,
+    pub names: Vec<String>,
+}
+
+/// Resolve the account that executes the instructions of a proposal of the SPL Governance
+/// account or Squads multisig at the target address, being the governance account itself
+/// or the default vault authority of the multisig.
+pub fn proposal_authority(
+    cluster: &Cluster,
+    commitment: CommitmentConfig,
+    target: &Pubkey,
+) -> Result<Pubkey> {
+    let rpc = RpcClient::new_with_commitment(cluster.url().to_owned(), commitment);
+    let target_account = rpc
+        .get_account(target)
+        .map_err(|_| anyhow!("proposal target {} does not exist", target))?;
+
+    if target_account.owner == Pubkey::from_str(SQUADS_MPL_ID)? {
+        return squads_vault_authority(target);
+    }
+
+    try_from_slice_unchecked::<GovernanceV2>(&target_account.data)
+        .map_err(|_| anyhow!("{} is not a governance or multisig account", target))?;
+
+    Ok(*target)
+}
+
+/// Wrap the argued instructions into a proposal of the SPL Governance account or
+/// Squads multisig at the target address, detected by the program that owns it.
+///
+/// The instructions are expected to be built for the proposal authority of the
+/// config, so that accounts derived from it are those used at execution. The
+/// remaining signer and fee payer accounts, such as rent payers, are replaced
+/// by the governance or multisig vault authority, which signs them at execution.
+pub fn wrap_as_proposal(
+    config: &Config,
+    target: &Pubkey,
+    instructions: Vec<Instruction>,
+    ix_names: &[&str],
+) -> Result<ProposalTransaction> {
+    let rpc = RpcClient::new_with_commitment(config.cluster.url().to_owned(), config.commitment);
+    let target_account = rpc
+        .get_account(target)
+        .map_err(|_| anyhow!("proposal target {} does not exist", target))?;
+
+    if target_account.owner == Pubkey::from_str(SQUADS_MPL_ID)? {
+        multisig_transaction(config, target, &target_account.data, instructions, ix_names)
+    } else {
+        governance_proposal(
+            config,
+            &rpc,
+            &target_account.owner,
+            target,
+            &target_account.data,
+            instructions,
+            ix_names,
+        )
+    }
+}
+
+/// Build the instructions for an SPL Governance proposal with the wrapped instructions
+/// inserted as a single transaction of its only option, signed off for voting.
+fn governance_proposal(
+    config: &Config,
+    rpc: &RpcClient,
+    governance_program: &Pubkey,
+    governance: &Pubkey,
+    data: &[u8],
+    instructions: Vec<Instruction>,
+    ix_names: &[&str],
+) -> Result<ProposalTransaction> {
+    let gov = try_from_slice_unchecked::<GovernanceV2>(data)
+        .map_err(|_| anyhow!("{} is not a governance or multisig account", governance))?;
+    let realm = try_from_slice_unchecked::<RealmV2>(&rpc.get_account_data(&gov.realm)?)?;
+
+    // Create the proposal with the council token owner record of the
+    // signer if it has one, otherwise with its community token owner record
+    let signer = config.keypair.pubkey();
+    let (governing_token_mint, token_owner_record) = realm
+        .config
+        .council_mint
+        .into_iter()
+        .chain([realm.community_mint])
+        .map(|mint| {
+            let record =
+                get_token_owner_record_address(governance_program, &gov.realm, &mint, &signer);
+            (mint, record)
+        })
+        .find(|(_, record)| rpc.get_account(record).is_ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "{} has no token owner record in realm {}",
+                signer,
+                gov.realm
+            )
+        })?;
+
+    let proposal = get_proposal_address(
+        governance_program,
+        governance,
+        &governing_token_mint,
+        &gov.proposals_count.to_le_bytes(),
+    );
+
+    let wrapped = replace_signers(config, instructions, governance)
+        .into_iter()
+        .map(InstructionData::from)
+        .collect();
+
+    Ok(ProposalTransaction {
+        address: proposal,
+        instructions: vec![
+            create_proposal(
+                governance_program,
+                governance,
+                &token_owner_record,
+                &signer,
+                &config.fee_payer.pubkey(),
+                None,
+                &gov.realm,
+                format!("jet: {}", ix_names.join(", ")),
+                String::new(),
+                &governing_token_mint,
+                VoteType::SingleChoice,
+                vec!["Approve".into()],
+                true,
+                gov.proposals_count,
+            ),
+            insert_transaction(
+                governance_program,
+                governance,
+                &proposal,
+                &token_owner_record,
+                &signer,
+                &config.fee_payer.pubkey(),
+                0,
+                0,
+                gov.config.min_instruction_hold_up_time,
+                wrapped,
+            ),
+            sign_off_proposal(
+                governance_program,
+                &gov.realm,
+                governance,
+                &proposal,
+                &signer,
+                Some(&token_owner_record),
+            ),
+        ],
+        names: vec![
+            "spl_governance::CreateProposal".into(),
+            format!(
+                "spl_governance::InsertTransaction -> {}",
+                ix_names.join(", ")
+            ),
+            "spl_governance::SignOffProposal".into(),
+        ],
+    })
+}
+
+/// Build the instructions for an activated Squads multisig transaction
+/// with an added multisig instruction for each wrapped instruction.
+fn multisig_transaction(
+    config: &Config,
+    multisig: &Pubkey,
+    data: &[u8],
+    instructions: Vec<Instruction>,
+    ix_names: &[&str],
+) -> Result<ProposalTransaction> {
+    if instructions.len() != ix_names.len() {
+        return Err(anyhow!(
+            "{} instructions to wrap but {} instruction names",
+            instructions.len(),
+            ix_names.len()
+        ));
+    }
+
+    // The transaction index follows the discriminator, threshold and authority index
+    let transaction_index = data
+        .get(12..16)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| anyhow!("{} is not a valid multisig account", multisig))?;
+
+    let squads_program = Pubkey::from_str(SQUADS_MPL_ID)?;
+    let creator = config.keypair.pubkey();
+    let authority = squads_vault_authority(multisig)?;
+    let (transaction, _) = Pubkey::find_program_address(
+        &[
+            b"squad",
+            multisig.as_ref(),
+            &(transaction_index + 1).to_le_bytes(),
+            b"transaction",
+        ],
+        &squads_program,
+    );
+
+    let mut ixs = vec![Instruction::new_with_bytes(
+        squads_program,
+        &squads_data(
+            "create_transaction",
+            &SQUADS_VAULT_AUTHORITY_INDEX.to_le_bytes(),
+        ),
+        vec![
+            AccountMeta::new(*multisig, false),
+            AccountMeta::new(transaction, false),
+            AccountMeta::new(creator, true),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    )];
+    let mut names = vec!["squads_mpl::CreateTransaction".to_owned()];
+
+    for (i, ix) in replace_signers(config, instructions, &authority)
+        .into_iter()
+        .enumerate()
+    {
+        let name = ix_names
+            .get(i)
+            .ok_or_else(|| anyhow!("missing the name of instruction {}", i))?;
+        let (instruction, _) = Pubkey::find_program_address(
+            &[
+                b"squad",
+                transaction.as_ref(),
+                &[i as u8 + 1],
+                b"instruction",
+            ],
+            &squads_program,
+        );
+
+        ixs.push(Instruction::new_with_bytes(
+            squads_program,
+            &squads_data("add_instruction", &encode_incoming_instruction(&ix)),
+            vec![
+                AccountMeta::new_readonly(*multisig, false),
+                AccountMeta::new(transaction, false),
+                AccountMeta::new(instruction, false),
+                AccountMeta::new(creator, true),
+                AccountMeta::new_readonly(system_program, false),
+            ],
+        ));
+        names.push(format!("squads_mpl::AddInstruction -> {}", name));
+    }
+
+    ixs.push(Instruction::new_with_bytes(
+        squads_program,
+        &squads_data("activate_transaction", &[]),
+        vec![
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new(transaction, false),
+            AccountMeta::new(creator, true),
+        ],
+    ));
+    names.push("squads_mpl::ActivateTransaction".into());
+
+    Ok(ProposalTransaction {
+        address: transaction,
+        instructions: ixs,
+        names,
+    })
+}
+
+/// Serialize an instruction as the Borsh encoded `IncomingInstruction` argument
+/// of the Squads `add_instruction` instruction.
+fn encode_incoming_instruction(ix: &Instruction) -> Vec<u8> {
+    let mut data = ix.program_id.to_bytes().to_vec();
+
+    data.extend((ix.accounts.len() as u32).to_le_bytes());
+    for meta in &ix.accounts {
+        data.extend(meta.pubkey.to_bytes());
+        data.push(meta.is_signer as u8);
+        data.push(meta.is_writable as u8);
+    }
+
+    data.extend((ix.data.len() as u32).to_le_bytes());
+    data.extend(&ix.data);
+    data
+}
+
+/// Replace the accounts of the configured signer and fee payer in the
+/// instructions with the authority that will execute the proposal.
+fn replace_signers(
+    config: &Config,
+    instructions: Vec<Instruction>,
+    authority: &Pubkey,
+) -> Vec<Instruction> {
+    let signers = [config.keypair.pubkey(), config.fee_payer.pubkey()];
+    instructions
+        .into_iter()
+        .map(|mut ix| {
+            ix.accounts
+                .iter_mut()
+                .filter(|meta| signers.contains(&meta.pubkey))
+                .for_each(|meta| meta.pubkey = *authority);
+            ix
+        })
+        .collect()
+}
+
+/// Derive the default vault authority of a Squads multisig.
+fn squads_vault_authority(multisig: &Pubkey) -> Result<Pubkey> {
+    let (authority, _) = Pubkey::find_program_address(
+        &[
+            b"squad",
+            multisig.as_ref(),
+            &SQUADS_VAULT_AUTHORITY_INDEX.to_le_bytes(),
+            b"authority",
+        ],
+        &Pubkey::from_str(SQUADS_MPL_ID)?,
+    );
+    Ok(authority)
+}
+
+/// Build the data for a Squads instruction from its Anchor
+/// sighash discriminator and the serialized arguments.
+fn squads_data(name: &str, args: &[u8]) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend(args);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_signers_with_authority() {
+        let config = Config::default();
+        let authority = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let ixs = replace_signers(
+            &config,
+            vec![Instruction::new_with_bytes(
+                Pubkey::default(),
+                &[],
+                vec![
+                    AccountMeta::new(config.keypair.pubkey(), true),
+                    AccountMeta::new_readonly(other, false),
+                ],
+            )],
+            &authority,
+        );
+
+        assert_eq!(ixs[0].accounts[0], AccountMeta::new(authority, true));
+        assert_eq!(ixs[0].accounts[1], AccountMeta::new_readonly(other, false));
+    }
+
+    #[test]
+    fn encode_squads_incoming_instruction() {
+        let ix = Instruction::new_with_bytes(
+            Pubkey::default(),
+            &[7, 8],
+            vec![AccountMeta::new_readonly(Pubkey::default(), true)],
+        );
+
+        let data = encode_incoming_instruction(&ix);
+        assert_eq!(data.len(), 32 + 4 + 34 + 4 + 2);
+        assert_eq!(&data[32..36], &[1, 0, 0, 0]);
+        assert_eq!(&data[68..70], &[1, 0]);
+        assert_eq!(&data[70..], &[2, 0, 0, 0, 7, 8]);
+
+        assert_eq!(squads_data("activate_transaction", &[1]).len(), 9);
+    }
+
+    #[test]
+    fn multisig_requires_instruction_names() {
+        let ix = Instruction::new_with_bytes(Pubkey::default(), &[], vec![]);
+        let result = multisig_transaction(
+            &Config::default(),
+            &Pubkey::default(),
+            &[0; 16],
+            vec![ix.clone(), ix],
+            &["jet_margin_pool::Configure"],
+        );
+        assert!(result.is_err());
+    }
+}
@@ -17,6 +17,14 @@
 
 use anchor_client::solana_sdk::pubkey::Pubkey;
 
+#[derive(Debug)]
+pub struct MarginPoolAddresses {
+    pub pool: Pubkey,
+    pub vault: Pubkey,
+    pub deposit_note_mint: Pubkey,
+    pub loan_note_mint: Pubkey,
+}
+
 #[derive(Debug)]
 pub struct StakePoolAddresses {
     pub pool: Pubkey,
@@ -24,11 +32,34 @@ pub struct StakePoolAddresses {
     pub collateral_mint: Pubkey,
 }
 
+/// Derive the public key of a `jet_metadata::MarginAdapterMetadata` program account.
+pub fn derive_adapter_metadata(adapter_program: &Pubkey, metadata_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[adapter_program.as_ref()], metadata_program).0
+}
+
 /// Derive the public key of a `jet_auth::UserAuthentication` program account.
 pub fn derive_auth_account(owner: &Pubkey, auth_program: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[owner.as_ref()], auth_program).0
 }
 
+/// Derive the public key of a `jet_margin::Liquidation` program account.
+pub fn derive_liquidation(
+    margin_account: &Pubkey,
+    liquidator: &Pubkey,
+    margin_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"liquidation", margin_account.as_ref(), liquidator.as_ref()],
+        margin_program,
+    )
+    .0
+}
+
+/// Derive the public key of a `jet_metadata::LiquidatorMetadata` program account.
+pub fn derive_liquidator_metadata(liquidator: &Pubkey, metadata_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[liquidator.as_ref()], metadata_program).0
+}
+
 /// Derive the public key of a `jet_margin::MarginAccount` program account.
 pub fn derive_margin_account(owner: &Pubkey, seed: u16, margin_program: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(
@@ -43,6 +74,29 @@ pub fn derive_margin_pool(token_mint: &Pubkey, margin_pool_program: &Pubkey) ->
     Pubkey::find_program_address(&[token_mint.as_ref()], margin_pool_program).0
 }
 
+/// Derive all the necessary public keys for creating a new
+/// `jet_margin_pool::MarginPool` program account.
+pub fn derive_margin_pool_addresses(
+    token_mint: &Pubkey,
+    margin_pool_program: &Pubkey,
+) -> MarginPoolAddresses {
+    let pool = derive_margin_pool(token_mint, margin_pool_program);
+    MarginPoolAddresses {
+        pool,
+        vault: Pubkey::find_program_address(&[pool.as_ref(), b"vault"], margin_pool_program).0,
+        deposit_note_mint: Pubkey::find_program_address(
+            &[pool.as_ref(), b"deposit-notes"],
+            margin_pool_program,
+        )
+        .0,
+        loan_note_mint: Pubkey::find_program_address(
+            &[pool.as_ref(), b"loan-notes"],
+            margin_pool_program,
+        )
+        .0,
+    }
+}
+
 /// Derive the public key of a governance max vote weight record program account.
 pub fn derive_max_voter_weight_record(realm: &Pubkey, staking_program: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(
@@ -52,6 +106,29 @@ pub fn derive_max_voter_weight_record(realm: &Pubkey, staking_program: &Pubkey)
     .0
 }
 
+/// Derive the public key of a `jet_metadata` program account entry for a key account and seed.
+pub fn derive_metadata_entry(
+    key_account: &Pubkey,
+    seed: &str,
+    metadata_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(&[key_account.as_ref(), seed.as_bytes()], metadata_program).0
+}
+
+/// Derive the public key of the token account that holds the balance
+/// of a registered position in a `jet_margin::MarginAccount`.
+pub fn derive_position_token_account(
+    margin_account: &Pubkey,
+    position_mint: &Pubkey,
+    margin_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[margin_account.as_ref(), position_mint.as_ref()],
+        margin_program,
+    )
+    .0
+}
+
 /// Derive the public key of a `jet_staking::state::StakeAccount` program account.
 pub fn derive_stake_account(
     stake_pool: &Pubkey,
@@ -95,6 +172,15 @@ pub fn derive_voter_weight_record(stake_account: &Pubkey, stake_program: &Pubkey
 mod tests {
     use super::*;
 
+    #[test]
+    fn derive_correct_adapter_metadata_address() {
+        let metadata = derive_adapter_metadata(&jet_margin_pool::ID, &jet_metadata::ID);
+        assert_eq!(
+            metadata.to_string(),
+            "x1AxdBLEj6awimpuEqRWkJNE5u3zPjLF3jVadVNG9A4"
+        );
+    }
+
     #[test]
     fn derive_correct_auth_address() {
         let auth = derive_auth_account(&Pubkey::default(), &jet_auth::ID);
@@ -104,6 +190,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derive_correct_liquidation_address() {
+        let liquidation =
+            derive_liquidation(&Pubkey::default(), &Pubkey::default(), &jet_margin::ID);
+        assert_eq!(
+            liquidation.to_string(),
+            "F6VswBeaHh89pNfuJebjkgfN244oDvJaFcVcofJ4pqP1"
+        );
+    }
+
+    #[test]
+    fn derive_correct_liquidator_metadata_address() {
+        let metadata = derive_liquidator_metadata(&Pubkey::default(), &jet_metadata::ID);
+        assert_eq!(
+            metadata.to_string(),
+            "6FHRbaZ2rnzfQJrv9M2uJ8jFGMdFoJtexvqZpSviYGhc"
+        );
+    }
+
     #[test]
     fn derive_correct_margin_address() {
         let margin = derive_margin_account(&Pubkey::default(), 15, &jet_margin::ID);
@@ -122,6 +227,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derive_correct_margin_pool_addrs() {
+        let addrs = derive_margin_pool_addresses(&Pubkey::default(), &jet_margin_pool::ID);
+
+        assert_eq!(
+            addrs.pool.to_string(),
+            "2ZuLUb2CsyXn1CKEL7Y76hWj8PnySPeJ77BKdX9JUarg"
+        );
+
+        assert_eq!(
+            addrs.vault.to_string(),
+            "7qZHucXL2jMFCyCiBL2NehCXgjzSxwWNCXtKr1QxzSnZ"
+        );
+
+        assert_eq!(
+            addrs.deposit_note_mint.to_string(),
+            "9yKhkp668s8ZuL13vr7NRak8PXrxFU3M7YCwTJLtMNar"
+        );
+
+        assert_eq!(
+            addrs.loan_note_mint.to_string(),
+            "JC4ngYhdq9ppEYUt6Mw6eWaVETHvSjF42TCZ2esAfTFb"
+        );
+    }
+
     #[test]
     fn derive_correct_max_vote_weight_record() {
         let record = derive_max_voter_weight_record(&Pubkey::default(), &jet_staking::ID);
@@ -131,6 +261,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derive_correct_metadata_entry_address() {
+        let entry = derive_metadata_entry(&Pubkey::default(), "position", &jet_metadata::ID);
+        assert_eq!(
+            entry.to_string(),
+            "EbMecYkYbi4yBeewjvgHxD55Ummkt4XqA2qG6mxAmy1s"
+        );
+        assert_eq!(
+            derive_metadata_entry(&Pubkey::default(), "", &jet_metadata::ID),
+            derive_liquidator_metadata(&Pubkey::default(), &jet_metadata::ID)
+        );
+    }
+
+    #[test]
+    fn derive_correct_position_token_account() {
+        let token_account =
+            derive_position_token_account(&Pubkey::default(), &Pubkey::default(), &jet_margin::ID);
+        assert_eq!(
+            token_account.to_string(),
+            "FPMfUGq9iyYyuFkNXhPCsBkvKDGU91EiS8JzZYWWQubC"
+        );
+    }
+
     #[test]
     fn derive_correct_staking_address() {
         let staking =
@@ -0,0 +1,449 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::solana_sdk::derivation_path::DerivationPath;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{read_keypair, Keypair, Signature};
+use anchor_client::solana_sdk::signer::keypair::{
+    generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed_and_derivation_path,
+};
+use anchor_client::solana_sdk::signer::{Signer, SignerError};
+use anyhow::{anyhow, Result};
+use dialoguer::Password;
+use solana_remote_wallet::locator::Locator;
+use solana_remote_wallet::remote_keypair::generate_remote_keypair;
+use solana_remote_wallet::remote_wallet::maybe_wallet_manager;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::read_to_string;
+use std::io::{stdin, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::config::normalize_path_arg;
+
+/// The length of the bytes of a keypair's secret and public keys.
+const KEYPAIR_LENGTH: usize = 64;
+
+/// The minimum decoded length of a base-58 value treated as an inline keypair
+/// rather than a file path, which is the length of a single 32 byte key.
+const INLINE_KEYPAIR_MIN_BYTES: usize = 32;
+
+/// The sources that a signer can be loaded from, parsed from a Solana-style
+/// signer URI or a plain keypair file path provided by the user.
+#[derive(PartialEq)]
+pub enum SignerSource {
+    /// `exec://<command> [args...]` for an external signer program.
+    Exec(Vec<String>),
+    /// `file://<path>` or a plain path to a JSON byte-array keypair file.
+    File(String),
+    /// An inline JSON byte-array or base-58 encoded keypair (e.g. from `JET_KEYPAIR`).
+    Inline(Vec<u8>),
+    /// `prompt://[?key=<account>/<change>]` to enter a seed phrase.
+    Prompt(Option<DerivationPath>),
+    /// `stdin://` to read a JSON byte-array keypair from standard input.
+    Stdin,
+    /// `usb://<manufacturer>[?key=<account>/<change>]` for a hardware wallet.
+    Usb {
+        locator: String,
+        derivation_path: Option<DerivationPath>,
+    },
+}
+
+impl fmt::Debug for SignerSource {
+    /// Format the signer source without the secret key of an inline keypair.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exec(command) => f.debug_tuple("Exec").field(command).finish(),
+            Self::File(path) => f.debug_tuple("File").field(path).finish(),
+            Self::Inline(_) => f.write_str("Inline(<redacted>)"),
+            Self::Prompt(derivation_path) => {
+                f.debug_tuple("Prompt").field(derivation_path).finish()
+            }
+            Self::Stdin => f.write_str("Stdin"),
+            Self::Usb {
+                locator,
+                derivation_path,
+            } => f
+                .debug_struct("Usb")
+                .field("locator", locator)
+                .field("derivation_path", derivation_path)
+                .finish(),
+        }
+    }
+}
+
+impl FromStr for SignerSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (scheme, rest) = match s.split_once("://") {
+            Some(parts) => parts,
+            None => {
+                return Ok(
+                    parse_inline_keypair(s)?.map_or_else(|| Self::File(s.to_owned()), Self::Inline)
+                )
+            }
+        };
+
+        let (location, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        match scheme {
+            "exec" => {
+                let args = rest
+                    .split_whitespace()
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>();
+                if args.is_empty() {
+                    return Err(anyhow!("`exec://` signers require a command"));
+                }
+                Ok(Self::Exec(args))
+            }
+            "file" => Ok(Self::File(rest.to_owned())),
+            "prompt" => Ok(Self::Prompt(parse_derivation_path(query)?)),
+            "stdin" => Ok(Self::Stdin),
+            "usb" => Ok(Self::Usb {
+                locator: format!("usb://{}", location),
+                derivation_path: parse_derivation_path(query)?,
+            }),
+            _ => Err(anyhow!("unsupported signer URI scheme `{}://`", scheme)),
+        }
+    }
+}
+
+/// A signer that delegates to an external program, which must print its base-58
+/// public key when invoked with `pubkey` and the base-58 signature of the hex
+/// encoded message that it reads from standard input when invoked with `sign`.
+#[derive(Debug)]
+pub struct ExternalSigner {
+    command: Vec<String>,
+    pubkey: Pubkey,
+}
+
+impl ExternalSigner {
+    /// Instantiate the signer by requesting the public key from the external program.
+    pub fn new(command: Vec<String>) -> Result<Self> {
+        let output = run_external(&command, "pubkey", None).map_err(|e| anyhow!("{}", e))?;
+        let pubkey = Pubkey::from_str(output.trim())
+            .map_err(|_| anyhow!("external signer returned an invalid public key: {}", output))?;
+        Ok(Self { command, pubkey })
+    }
+}
+
+impl Signer for ExternalSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let hex = message
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        let output = run_external(&self.command, "sign", Some(&hex))?;
+        Signature::from_str(output.trim()).map_err(|_| {
+            SignerError::Custom(format!(
+                "external signer returned an invalid signature: {}",
+                output
+            ))
+        })
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+/// A signer that is only loaded from its signer URI or keypair file path when its
+/// public key or a signature is first required, so that commands which never sign
+/// can run without a wallet and do not prompt for seed phrases or hardware wallets.
+pub struct LazySigner {
+    name: String,
+    signer: RefCell<Option<Rc<dyn Signer>>>,
+    uri: String,
+}
+
+impl LazySigner {
+    /// Instantiate the signer for the argued URI without loading it.
+    pub fn new(uri: &str, name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            signer: RefCell::new(None),
+            uri: uri.to_owned(),
+        }
+    }
+
+    /// Load the signer from its URI if it has not been loaded yet.
+    fn load(&self) -> Result<Rc<dyn Signer>, SignerError> {
+        if let Some(signer) = self.signer.borrow().as_ref() {
+            return Ok(signer.clone());
+        }
+
+        // Only keypair file paths are echoed, as other URIs may hold an inline secret key
+        let source = match SignerSource::from_str(&self.uri) {
+            Ok(SignerSource::File(path)) => format!(" `{}`", path),
+            _ => String::new(),
+        };
+
+        let signer = signer_from_uri(&self.uri, &self.name).map_err(|e| {
+            SignerError::Custom(format!(
+                "failed to load the {} signer{}: {}",
+                self.name, source, e
+            ))
+        })?;
+
+        *self.signer.borrow_mut() = Some(signer.clone());
+        Ok(signer)
+    }
+}
+
+impl Signer for LazySigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.load()?.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.load()?.try_sign_message(message)
+    }
+
+    /// Whether the loaded signer is interactive or, if it has not been loaded yet,
+    /// whether loading it would prompt, read standard input or use an external device.
+    fn is_interactive(&self) -> bool {
+        match self.signer.borrow().as_ref() {
+            Some(signer) => signer.is_interactive(),
+            None => !matches!(
+                SignerSource::from_str(&self.uri),
+                Ok(SignerSource::File(_) | SignerSource::Inline(_))
+            ),
+        }
+    }
+}
+
+/// Load the signer for the argued signer URI or keypair file path.
+pub fn signer_from_uri(uri: &str, name: &str) -> Result<Rc<dyn Signer>> {
+    let signer: Rc<dyn Signer> = match SignerSource::from_str(uri)? {
+        SignerSource::Exec(command) => Rc::new(ExternalSigner::new(command)?),
+        SignerSource::File(path) => {
+            let data = read_to_string(normalize_path_arg(name, &path)?)?;
+            let bytes: Vec<u8> = serde_json::from_str(&data)?;
+            Rc::new(Keypair::from_bytes(&bytes)?)
+        }
+        SignerSource::Inline(bytes) => Rc::new(
+            Keypair::from_bytes(&bytes).map_err(|e| anyhow!("invalid inline keypair: {}", e))?,
+        ),
+        SignerSource::Prompt(derivation_path) => {
+            let phrase = Password::new()
+                .with_prompt(format!("[{}] seed phrase", name))
+                .interact()?;
+            let passphrase = Password::new()
+                .with_prompt(format!("[{}] passphrase (empty for none)", name))
+                .allow_empty_password(true)
+                .interact()?;
+
+            let seed = generate_seed_from_seed_phrase_and_passphrase(&phrase, &passphrase);
+            Rc::new(
+                keypair_from_seed_and_derivation_path(&seed, derivation_path)
+                    .map_err(|e| anyhow!("failed to derive keypair from seed phrase: {}", e))?,
+            )
+        }
+        SignerSource::Stdin => Rc::new(
+            read_keypair(&mut stdin())
+                .map_err(|e| anyhow!("failed to read keypair from stdin: {}", e))?,
+        ),
+        SignerSource::Usb {
+            locator,
+            derivation_path,
+        } => {
+            let wallet_manager = maybe_wallet_manager()
+                .map_err(|e| anyhow!("{}", e))?
+                .ok_or_else(|| anyhow!("no hardware wallets were found"))?;
+            let locator = Locator::new_from_path(&locator)
+                .map_err(|e| anyhow!("invalid hardware wallet `{}`: {}", locator, e))?;
+
+            Rc::new(
+                generate_remote_keypair(
+                    locator,
+                    derivation_path.unwrap_or_default(),
+                    &wallet_manager,
+                    false,
+                    name,
+                )
+                .map_err(|e| anyhow!("{}", e))?,
+            )
+        }
+    };
+
+    Ok(signer)
+}
+
+/// Parse the bytes of a keypair provided inline as a JSON byte array or a base-58
+/// string, returning `None` if the value should instead be treated as a file path.
+///
+/// Values that are not an existing path but resemble an inline keypair are rejected
+/// rather than treated as a file path, so that a malformed secret key is never echoed.
+fn parse_inline_keypair(s: &str) -> Result<Option<Vec<u8>>> {
+    let s = s.trim();
+    if Path::new(s).exists() {
+        return Ok(None);
+    }
+
+    let bytes: Vec<u8> = if s.starts_with('[') {
+        serde_json::from_str(s).map_err(|_| anyhow!("invalid inline JSON byte-array keypair"))?
+    } else {
+        match bs58::decode(s).into_vec() {
+            Ok(bytes) if bytes.len() >= INLINE_KEYPAIR_MIN_BYTES => bytes,
+            _ => return Ok(None),
+        }
+    };
+
+    if bytes.len() != KEYPAIR_LENGTH {
+        return Err(anyhow!(
+            "invalid inline keypair of {} bytes, expected {}",
+            bytes.len(),
+            KEYPAIR_LENGTH
+        ));
+    }
+
+    Ok(Some(bytes))
+}
+
+/// Parse the derivation path from the `key=<account>/<change>` query of a signer URI.
+fn parse_derivation_path(query: &str) -> Result<Option<DerivationPath>> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("key="))
+        .map(|key| {
+            DerivationPath::from_key_str(key)
+                .map_err(|e| anyhow!("invalid derivation path `{}`: {}", key, e))
+        })
+        .transpose()
+}
+
+/// Invoke the external signer program with the argued action and optional
+/// standard input, returning its standard output when it exits successfully.
+fn run_external(
+    command: &[String],
+    action: &str,
+    input: Option<&str>,
+) -> Result<String, SignerError> {
+    let err = |e: std::io::Error| SignerError::Custom(format!("external signer failed: {}", e));
+
+    let mut child = Command::new(shellexpand::tilde(&command[0]).as_ref())
+        .args(&command[1..])
+        .arg(action)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(err)?;
+
+    if let Some(data) = input {
+        child
+            .stdin
+            .take()
+            .expect("piped stdin")
+            .write_all(data.as_bytes())
+            .map_err(err)?;
+    }
+
+    let output = child.wait_with_output().map_err(err)?;
+    if !output.status.success() {
+        return Err(SignerError::Custom(format!(
+            "external signer exited with {}",
+            output.status
+        )));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|_| SignerError::Custom("external signer output was not UTF-8".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lazy_signer_loads_on_use() {
+        let signer = LazySigner::new("/tmp/jet-missing-keypair.json", "--keypair");
+        assert!(signer.signer.borrow().is_none());
+        assert!(!signer.is_interactive());
+        assert!(LazySigner::new("prompt://", "--keypair").is_interactive());
+        assert!(signer.try_pubkey().is_err());
+
+        let keypair = Keypair::new();
+        let signer = LazySigner::new(&keypair.to_base58_string(), "--keypair");
+        assert_eq!(signer.try_pubkey().unwrap(), keypair.pubkey());
+        assert!(signer.signer.borrow().is_some());
+    }
+
+    #[test]
+    fn parse_signer_sources() {
+        assert_eq!(
+            SignerSource::from_str("~/.config/solana/id.json").unwrap(),
+            SignerSource::File("~/.config/solana/id.json".into())
+        );
+        assert_eq!(
+            SignerSource::from_str("file:///tmp/id.json").unwrap(),
+            SignerSource::File("/tmp/id.json".into())
+        );
+        assert_eq!(
+            SignerSource::from_str("stdin://").unwrap(),
+            SignerSource::Stdin
+        );
+        assert_eq!(
+            SignerSource::from_str("prompt://").unwrap(),
+            SignerSource::Prompt(None)
+        );
+        assert_eq!(
+            SignerSource::from_str("usb://ledger?key=0/1").unwrap(),
+            SignerSource::Usb {
+                locator: "usb://ledger".into(),
+                derivation_path: Some(DerivationPath::new_bip44(Some(0), Some(1))),
+            }
+        );
+        assert_eq!(
+            SignerSource::from_str("exec://jet-signer --account 1").unwrap(),
+            SignerSource::Exec(vec!["jet-signer".into(), "--account".into(), "1".into()])
+        );
+
+        let keypair = Keypair::new();
+        assert_eq!(
+            SignerSource::from_str(&keypair.to_base58_string()).unwrap(),
+            SignerSource::Inline(keypair.to_bytes().to_vec())
+        );
+        assert_eq!(
+            SignerSource::from_str(&format!("{:?}", keypair.to_bytes().to_vec())).unwrap(),
+            SignerSource::Inline(keypair.to_bytes().to_vec())
+        );
+
+        let truncated = bs58::encode(&keypair.to_bytes()[..48]).into_string();
+        let err = SignerSource::from_str(&truncated).unwrap_err().to_string();
+        assert!(!err.contains(&truncated));
+        assert!(SignerSource::from_str("[1, 2, 3]").is_err());
+        assert_eq!(
+            format!(
+                "{:?}",
+                SignerSource::from_str(&keypair.to_base58_string()).unwrap()
+            ),
+            "Inline(<redacted>)"
+        );
+
+        assert!(SignerSource::from_str("exec://").is_err());
+        assert!(SignerSource::from_str("usb://ledger?key=a/b").is_err());
+        assert!(SignerSource::from_str("ftp://keys").is_err());
+    }
+}
@@ -18,6 +18,7 @@ use dialoguer::Confirm;
 use indicatif::{ProgressBar, ProgressStyle};
 use serde::ser::Serialize;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
 use super::config::Config;
@@ -27,13 +28,25 @@ use super::config::Config;
 /// program account data struct.
 pub struct DisplayOptions {
     json: bool,
+    labels: HashMap<String, String>,
     pretty: bool,
 }
 
 impl DisplayOptions {
     /// Instantiate based on the values of the argument options.
     pub fn from_args(json: bool, pretty: bool) -> Self {
-        Self { json, pretty }
+        Self {
+            json,
+            labels: HashMap::new(),
+            pretty,
+        }
+    }
+
+    /// Set the address labels keyed by public key that annotate the
+    /// known public keys in the non-JSON output.
+    pub fn with_labels(mut self, labels: &HashMap<String, String>) -> Self {
+        self.labels = labels.clone();
+        self
     }
 }
 
@@ -68,7 +81,8 @@ impl Spinner {
 
 /// Standardize function for printing structs that implement both `std::fmt::Debug`
 /// and `serde::ser::Serialize` (JSON) to be printed to the terminal is either format
-/// with the option to be pretty printed.
+/// with the option to be pretty printed. Known public keys are annotated with their
+/// address labels in the non-JSON output so it remains parseable.
 pub fn print_serialized(s: impl Debug + Serialize, opts: &DisplayOptions) -> Result<()> {
     if opts.json {
         println!(
@@ -82,15 +96,61 @@ pub fn print_serialized(s: impl Debug + Serialize, opts: &DisplayOptions) -> Res
         return Ok(());
     }
 
-    if opts.pretty {
-        println!("{:#?}", s);
+    let output = if opts.pretty {
+        format!("{:#?}", s)
     } else {
-        println!("{:?}", s);
-    }
+        format!("{:?}", s)
+    };
+
+    println!("{}", annotate_labels(output, &opts.labels));
 
     Ok(())
 }
 
+/// Append the label of each known public key in the output after its occurrences.
+fn annotate_labels(output: String, labels: &HashMap<String, String>) -> String {
+    labels.iter().fold(output, |out, (addr, label)| {
+        out.replace(addr.as_str(), &format!("{} ({})", addr, label))
+    })
+}
+
+/// Print the argued rows of string cells as a left-aligned table with
+/// the column widths fitted to the longest value or header in each.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if let Some(w) = widths.get_mut(i) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(c, w)| format!("{:<width$}", c, width = *w))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<String>>()
+            .join("  ")
+    );
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
 /// Provides the user a confirmation `(y/N)` option in their terminal
 /// to request approval to sign and send the compiled transaction(s)
 /// using the configured keypair that was discovered or pointed to
@@ -123,3 +183,25 @@ pub fn request_approval(config: &Config, ixs: Option<Vec<&str>>) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotate_known_addresses() {
+        let labels = HashMap::from([(
+            "11111111111111111111111111111111".to_owned(),
+            "System Program".to_owned(),
+        )]);
+
+        assert_eq!(
+            annotate_labels("owner: 11111111111111111111111111111111".into(), &labels),
+            "owner: 11111111111111111111111111111111 (System Program)"
+        );
+        assert_eq!(
+            annotate_labels("owner: none".into(), &labels),
+            "owner: none"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+// Copyright (C) 2022 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Cluster;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::amount::TokenAmount;
+use crate::config::{cluster_name, parse_address, JetConfigFile};
+
+/// The bundled mainnet tokens as their symbol, mint, decimals and display name,
+/// which are only known to the registry when the configured cluster is mainnet.
+const BUNDLED_TOKENS: &[(&str, &str, u8, &str)] = &[
+    (
+        "BTC",
+        "9n4nbM75f5Ui33ZbPYXn59EwSgE8CGsHtAeTH5YFeJ9E",
+        6,
+        "Wrapped Bitcoin (Sollet)",
+    ),
+    (
+        "ETH",
+        "7vfCXTUXx5WJV5JADk17DUJ4ksgau7utNKj4b963voxs",
+        8,
+        "Ether (Portal)",
+    ),
+    (
+        "JET",
+        "JET6zMJWkCN9tpRT2v2jfAmm5VnQFDpUBCyaKojmGtz",
+        9,
+        "Jet Protocol",
+    ),
+    (
+        "MSOL",
+        "mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So",
+        9,
+        "Marinade Staked SOL",
+    ),
+    (
+        "SOL",
+        "So11111111111111111111111111111111111111112",
+        9,
+        "Wrapped SOL",
+    ),
+    (
+        "USDC",
+        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        6,
+        "USD Coin",
+    ),
+    (
+        "USDT",
+        "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB",
+        6,
+        "USDT",
+    ),
+];
+
+/// A user defined token in a `[tokens.<cluster>.<SYMBOL>]` table of the Jet configuration file.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TokenConfig {
+    pub decimals: u8,
+    pub mint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// The symbol, decimals and display name of a token mint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenInfo {
+    pub decimals: u8,
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+}
+
+/// A token mint argument that is either a public key or the name of a token
+/// symbol or address label, resolved against the registry of the configured cluster.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenMint {
+    /// A base-58 token mint public key.
+    Address(Pubkey),
+    /// A token symbol (e.g. `USDC`) or a label of the address book.
+    Name(String),
+}
+
+impl FromStr for TokenMint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let trimmed = s.trim();
+        if trimmed.is_empty() {
+            return Err(anyhow!("a token mint, symbol or address label is required"));
+        }
+
+        match Pubkey::from_str(trimmed) {
+            Ok(pk) => Ok(Self::Address(pk)),
+            Err(_) => Ok(Self::Name(trimmed.to_owned())),
+        }
+    }
+}
+
+/// The registry of known tokens of a cluster from the bundled mainnet tokens
+/// extended, and overridden by symbol, by the user defined tokens of the cluster.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenRegistry(Vec<TokenInfo>);
+
+impl Default for TokenRegistry {
+    fn default() -> Self {
+        Self(
+            BUNDLED_TOKENS
+                .iter()
+                .map(|(symbol, mint, decimals, name)| TokenInfo {
+                    decimals: *decimals,
+                    mint: Pubkey::from_str(mint).unwrap(),
+                    name: (*name).to_owned(),
+                    symbol: (*symbol).to_owned(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl TokenRegistry {
+    /// Create the registry of the argued cluster from the bundled tokens if it is
+    /// mainnet and the tokens defined for the cluster in the configuration file.
+    pub fn for_cluster(cluster: &Cluster, file: &JetConfigFile) -> Result<Self> {
+        let name = cluster_name(cluster);
+        let mut registry = if name == Cluster::Mainnet.to_string() {
+            Self::default()
+        } else {
+            Self(Vec::new())
+        };
+
+        let tokens = match file.tokens.get(&name) {
+            Some(t) => t,
+            None => return Ok(registry),
+        };
+
+        for (symbol, token) in tokens {
+            let mint = Pubkey::from_str(&token.mint)
+                .map_err(|_| anyhow!("invalid mint for token `{}`: {}", symbol, token.mint))?;
+
+            registry
+                .0
+                .retain(|t| !t.symbol.eq_ignore_ascii_case(symbol) && t.mint != mint);
+            registry.0.push(TokenInfo {
+                decimals: token.decimals,
+                mint,
+                name: token.name.clone().unwrap_or_else(|| symbol.clone()),
+                symbol: symbol.clone(),
+            });
+        }
+        Ok(registry)
+    }
+
+    /// Find the token of the argued mint.
+    pub fn find_by_mint(&self, mint: &Pubkey) -> Option<&TokenInfo> {
+        self.0.iter().find(|t| t.mint == *mint)
+    }
+
+    /// Find the token with the argued symbol, ignoring case.
+    pub fn find_by_symbol(&self, symbol: &str) -> Option<&TokenInfo> {
+        self.0
+            .iter()
+            .find(|t| t.symbol.eq_ignore_ascii_case(symbol))
+    }
+
+    /// Iterate over the known tokens of the cluster.
+    pub fn iter(&self) -> impl Iterator<Item = &TokenInfo> {
+        self.0.iter()
+    }
+
+    /// Resolve a token mint argument to its public key from a symbol of the registry or,
+    /// otherwise, a label of the address book, so symbols of other clusters are an error.
+    pub fn resolve(&self, mint: &TokenMint) -> Result<Pubkey> {
+        let name = match mint {
+            TokenMint::Address(pk) => return Ok(*pk),
+            TokenMint::Name(name) => name,
+        };
+
+        match self.find_by_symbol(name) {
+            Some(token) => Ok(token.mint),
+            None => parse_address(name).map_err(|_| {
+                anyhow!(
+                    "`{}` is not a token mint, address label or known token symbol of the cluster",
+                    name
+                )
+            }),
+        }
+    }
+
+    /// The symbol of the argued mint if it is known.
+    pub fn symbol(&self, mint: &Pubkey) -> Option<&str> {
+        self.find_by_mint(mint).map(|t| t.symbol.as_str())
+    }
+
+    /// The symbol of the argued mint if it is known, otherwise the mint's public key.
+    pub fn symbol_or_mint(&self, mint: &Pubkey) -> String {
+        self.symbol(mint)
+            .map_or_else(|| mint.to_string(), ToOwned::to_owned)
+    }
+
+    /// Verify that the symbol of an amount, if it has one, is the symbol of the argued mint.
+    pub fn verify_symbol(&self, amount: &TokenAmount, mint: &Pubkey) -> Result<()> {
+        let symbol = match amount {
+            TokenAmount::Value {
+                symbol: Some(s), ..
+            } => s,
+            _ => return Ok(()),
+        };
+
+        let token = self
+            .find_by_symbol(symbol)
+            .ok_or_else(|| anyhow!("unknown token symbol `{}` in amount", symbol))?;
+
+        if token.mint != *mint {
+            return Err(anyhow!(
+                "amount is denominated in {} but the token mint is {}",
+                token.symbol,
+                self.symbol_or_mint(mint)
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_registry_lookups() {
+        let file: JetConfigFile = toml::from_str(
+            r#"
+            [tokens.mainnet.USDC]
+            mint = "11111111111111111111111111111111"
+            decimals = 6
+
+            [tokens.mainnet.DEVJET]
+            mint = "JET6zMJWkCN9tpRT2v2jfAmm5VnQFDpUBCyaKojmGtz"
+            decimals = 9
+            name = "Devnet Jet"
+
+            [tokens.devnet.USDC]
+            mint = "11111111111111111111111111111111"
+            decimals = 6
+            "#,
+        )
+        .unwrap();
+
+        let default = TokenRegistry::default();
+        let sol = default.find_by_symbol("sol").unwrap();
+        assert_eq!(sol.decimals, 9);
+        assert_eq!(default.symbol_or_mint(&sol.mint), "SOL");
+        assert_eq!(
+            default.symbol_or_mint(&Pubkey::default()),
+            Pubkey::default().to_string()
+        );
+
+        let registry = TokenRegistry::for_cluster(&Cluster::Mainnet, &file).unwrap();
+        assert_eq!(
+            registry.find_by_symbol("USDC").unwrap().mint,
+            Pubkey::default()
+        );
+        assert_eq!(registry.find_by_symbol("JET"), None);
+        assert_eq!(
+            registry.find_by_symbol("devjet").unwrap().name,
+            "Devnet Jet"
+        );
+
+        // Bundled mainnet tokens are not known on other clusters
+        let devnet = TokenRegistry::for_cluster(&Cluster::Devnet, &file).unwrap();
+        assert_eq!(devnet.iter().count(), 1);
+        assert_eq!(devnet.find_by_symbol("SOL"), None);
+        assert!(TokenRegistry::for_cluster(&Cluster::Localnet, &file)
+            .unwrap()
+            .iter()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn resolve_token_mints() {
+        let registry = TokenRegistry::default();
+        let usdc = registry.find_by_symbol("USDC").unwrap().mint;
+
+        assert_eq!(
+            TokenMint::from_str(&usdc.to_string()).unwrap(),
+            TokenMint::Address(usdc)
+        );
+        assert_eq!(
+            registry
+                .resolve(&TokenMint::from_str("usdc").unwrap())
+                .unwrap(),
+            usdc
+        );
+        assert!(TokenMint::from_str(" ").is_err());
+
+        let devnet =
+            TokenRegistry::for_cluster(&Cluster::Devnet, &JetConfigFile::default()).unwrap();
+        assert!(devnet
+            .resolve(&TokenMint::Name("jet-test-unknown-symbol".into()))
+            .is_err());
+        assert_eq!(devnet.resolve(&TokenMint::Address(usdc)).unwrap(), usdc);
+    }
+
+    #[test]
+    fn verify_amount_symbols() {
+        let registry = TokenRegistry::default();
+        let usdc = registry.find_by_symbol("USDC").unwrap().mint;
+        let sol = registry.find_by_symbol("SOL").unwrap().mint;
+
+        let amount = TokenAmount::from_str("1.5 usdc").unwrap();
+        assert!(registry.verify_symbol(&amount, &usdc).is_ok());
+        assert!(registry.verify_symbol(&amount, &sol).is_err());
+        assert!(registry
+            .verify_symbol(&TokenAmount::from_str("1 XYZ").unwrap(), &usdc)
+            .is_err());
+        assert!(registry.verify_symbol(&TokenAmount::Max, &sol).is_ok());
+    }
+}